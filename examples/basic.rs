@@ -1,23 +1,25 @@
+#![allow(clippy::needless_range_loop)]
+
 use std::collections::HashMap;
 
 use ggsdk::{
     GGAtlas, GGRunOptions,
     egui::{self, Align2, Color32, FontId, Key, LayerId},
 };
-use glam::{Vec2, Vec3, Vec4};
-use glow::HasContext;
-use glox::{Camera, FirstPersonCamera, Glox, OrbitalCamera};
+use glam::{Mat4, Vec2, Vec3, Vec4};
+use glox::{
+    Atlas, AtlasBuilder, BlendMode, Camera, DepthState, DirectionalLight, DrawState,
+    FirstPersonCamera, FrameDesc, Glox, GloxConfig, GloxError, ImageOptions, Instance, Lights,
+    LitVertex, Mesh, OrbitalCamera, PointLight, RenderQueue, TextureOptions, Topology, UvRect,
+    Vertex,
+};
 
-#[derive(PartialEq, Eq)]
+#[derive(PartialEq, Eq, Default)]
 pub enum ChosenCamera {
     Orbital,
+    #[default]
     FirstPerson,
 }
-impl Default for ChosenCamera {
-    fn default() -> Self {
-        Self::FirstPerson
-    }
-}
 
 #[derive(Default)]
 struct App {
@@ -26,6 +28,10 @@ struct App {
     pub fps_camera: FirstPersonCamera,
     pub chosen_camera: ChosenCamera,
     pub cursor_grab: bool,
+    pub level: Option<Mesh>,
+    pub billboard: Option<Mesh>,
+    pub sprites: Option<Atlas>,
+    pub queue: RenderQueue,
 }
 
 static MAP: [[u8; 8]; 8] = [
@@ -41,23 +47,31 @@ static MAP: [[u8; 8]; 8] = [
 
 impl ggsdk::GGApp for App {
     fn init(&mut self, g: ggsdk::InitContext) {
-        self.glox.init(g.gl);
+        self.glox = Glox::with_config(GloxConfig {
+            depth: Some(DepthState::default()),
+            ..Default::default()
+        });
+        if let Err(err) = self.glox.init(g.gl) {
+            eprintln!("failed to initialize glox: {err}");
+        }
+        match level_mesh(g.gl) {
+            Ok(level) => self.level = Some(level),
+            Err(err) => eprintln!("failed to create level mesh: {err}"),
+        }
+        match Mesh::billboard(g.gl) {
+            Ok(billboard) => self.billboard = Some(billboard),
+            Err(err) => eprintln!("failed to create billboard mesh: {err}"),
+        }
+        match sprite_atlas(&mut self.glox, g.gl) {
+            Ok(sprites) => self.sprites = Some(sprites),
+            Err(err) => eprintln!("failed to create sprite atlas: {err}"),
+        }
         self.orbital_camera.eye = Vec3::new(0.0, -10.0, 10.0);
         self.orbital_camera.target = Vec3::default();
         self.fps_camera.eye = Vec3::new(2.5, 2.5, 0.5);
 
         g.assets
             .load::<GGAtlas>("examples/imgs/wall_1x1.png", "wall");
-        g.assets
-            .load::<GGAtlas>("examples/imgs/cross_1x1.png", "cross");
-        g.assets
-            .load::<GGAtlas>("examples/imgs/lamp_1x1.png", "lamp");
-        g.assets
-            .load::<GGAtlas>("examples/imgs/plant_1x1.png", "plant");
-        g.assets
-            .load::<GGAtlas>("examples/imgs/chairs_1x1.png", "chairs");
-        g.assets
-            .load::<GGAtlas>("examples/imgs/player_1x1.png", "player");
     }
 
     fn update(&mut self, g: ggsdk::UpdateContext) {
@@ -83,6 +97,11 @@ impl ggsdk::GGApp for App {
                 ChosenCamera::FirstPerson,
                 "First Person Camera",
             );
+            ui.separator();
+            let stats = self.glox.stats();
+            ui.label(format!("draw calls: {}", stats.draw_calls));
+            ui.label(format!("texture binds: {}", stats.texture_binds));
+            ui.label(format!("uploaded: {} bytes", stats.bytes_uploaded));
         });
     }
 
@@ -99,7 +118,7 @@ impl ggsdk::GGApp for App {
                 self.cursor_grab = !self.cursor_grab;
             }
 
-            if self.cursor_grab == false {
+            if !self.cursor_grab {
                 return;
             }
 
@@ -174,6 +193,19 @@ impl ggsdk::GGApp for App {
     }
 
     fn paint_glow(&mut self, g: ggsdk::PaintGlowContext) {
+        // begin_frame and end_frame leave the GL state as egui expects it
+        let painter = g.painter;
+        self.glox.begin_frame(
+            painter.gl(),
+            FrameDesc::from_config(self.glox.config()),
+        );
+        self.paint_scene(g);
+        self.glox.end_frame(painter.gl());
+    }
+}
+
+impl App {
+    fn paint_scene(&mut self, g: ggsdk::PaintGlowContext) {
         let camera: &dyn Camera = match self.chosen_camera {
             ChosenCamera::Orbital => &self.orbital_camera,
             ChosenCamera::FirstPerson => &self.fps_camera,
@@ -184,96 +216,48 @@ impl ggsdk::GGApp for App {
         let texture = g.painter.texture(texture.texture_id()).unwrap();
         let camera_dir = camera.direction();
         let gl = g.painter.gl();
-        unsafe {
-            gl.enable(glow::DEPTH_TEST);
-            gl.clear(glow::COLOR_BUFFER_BIT | glow::DEPTH_BUFFER_BIT);
-        }
-        // draw walls
-        let mut walls = HashMap::new();
         let size = MAP.len();
 
-        for y in 0..size {
-            for x in 0..size {
-                let cell = MAP[y][x];
-                if cell == 1 {
-                    let x_i = x as i32;
-                    let y_i = y as i32;
-
-                    // Check adjacent cells before adding walls
-                    let has_top = y > 0 && MAP[y - 1][x] == 1;
-                    let has_right = x < size - 1 && MAP[y][x + 1] == 1;
-                    let has_bottom = y < size - 1 && MAP[y + 1][x] == 1;
-                    let has_left = x > 0 && MAP[y][x - 1] == 1;
-
-                    if !has_top {
-                        walls.insert((x_i, y_i, true), ()); // top wall
-                    }
-                    if !has_right {
-                        walls.insert((x_i + 1, y_i, false), ()); // right wall
-                    }
-                    if !has_bottom {
-                        walls.insert((x_i, y_i + 1, true), ()); // bottom wall
-                    }
-                    if !has_left {
-                        walls.insert((x_i, y_i, false), ()); // left wall
-                    }
-                }
-            }
-        }
+        // a dim sun, a warm light at the lamp and one carried by the player
+        let lights = Lights {
+            ambient: Vec3::splat(0.3),
+            directional: Some(DirectionalLight {
+                direction: Vec3::new(-1.0, 2.0, -3.0),
+                color: Vec3::splat(0.4),
+            }),
+            point_lights: vec![
+                PointLight::new(Vec3::new(6.5, 6.5, 0.8), Vec3::new(1.0, 0.8, 0.5), 4.0),
+                PointLight::new(self.fps_camera.eye, Vec3::splat(0.6), 3.0),
+            ],
+        };
 
-        // draw all walls
-        let mut draw = self.glox.draw_builder(gl, camera);
+        let Ok(mut draw) = self.glox.draw_builder_with_layout::<LitVertex>(gl, camera) else {
+            return;
+        };
+        draw.blend_mode(BlendMode::Opaque);
+        draw.lights(Some(&lights));
         draw.push_vertices(&glox::plane_vertices(
             Default::default(),
             Vec4::new(0.4, 0.4, 0.4, 1.0),
             1024.0,
         ));
-        draw.finish();
 
-        let mut draw = self.glox.draw_builder(gl, camera);
-        draw.bind_texture(Some(texture));
-
-        for (x, y, top) in walls.keys() {
-            let n = match top {
-                true => Vec3::new(0.0, 1.0, 0.0),
-                false => Vec3::new(1.0, 0.0, 0.0),
-            };
-            let c = 0.5;
-            let color = match top {
-                true => Vec4::new(1.0, 1.0, 1.0, 1.0),
-                false => Vec4::new(c, c, c, 1.0),
-            };
-            let p = match top {
-                true => Vec3::new(*x as f32 + 0.5, *y as f32, 0.0),
-                false => Vec3::new(*x as f32, *y as f32 + 0.5, 0.0),
-            };
-
-            draw.push_vertices(&glox::wall_vertices(p, 1.0, color, n));
+        // draw walls and top of blocks
+        if let Some(level) = &self.level {
+            draw.bind_texture(Some(texture));
+            draw.draw_mesh(level, Mat4::IDENTITY);
         }
         draw.finish();
 
-        // draw top of block
-        let mut draw = self.glox.draw_builder(gl, camera);
-        draw.bind_texture(Some(texture));
-        for y in 0..size {
-            for x in 0..size {
-                if MAP[y][x] != 1 {
-                    continue;
-                }
-                let p = Vec3::new(x as f32 + 0.5, y as f32 + 0.5, 1.0);
-                let color = Vec4::new(0.2, 0.2, 0.2, 1.0);
-                draw.push_vertices(&glox::floor_vertices(p, color));
-            }
-        }
-        draw.finish();
+        let Some(sprites) = &self.sprites else {
+            return;
+        };
 
-        // draw some sprites / billboards
+        // draw some sprites / billboards, all from the same atlas in one instanced draw
+        let mut instances = Vec::new();
         for y in 0..size {
             for x in 0..size {
-                let mut draw = self.glox.draw_builder(gl, camera);
-                //draw.bind_texture(Some(texture));
-                let id = MAP[y][x];
-                let texture = match id {
+                let sprite = match MAP[y][x] {
                     2 => "cross",
                     3 => "plant",
                     4 => "chairs",
@@ -282,40 +266,147 @@ impl ggsdk::GGApp for App {
                         continue;
                     }
                 };
-                if let Some(atlas) = g.assets.get::<GGAtlas>(texture) {
-                    let texture = g.painter.texture(atlas.texture_id()).unwrap();
-                    draw.bind_texture(texture.into());
-                }
+                let Some(uv) = sprites.region(sprite) else {
+                    continue;
+                };
                 let p = Vec3::new(x as f32 + 0.5, y as f32 + 0.5, 0.0);
-                draw.push_vertices(&glox::billboard_vertices(
-                    p,
-                    Vec4::splat(1.0),
-                    camera_dir,
-                    Vec2::splat(1.0),
-                ));
-                draw.finish();
+                instances.push(
+                    Instance::billboard(p, Vec2::splat(1.0), Vec4::splat(1.0)).with_uv_rect(uv),
+                );
             }
         }
+        if let Some(billboard) = &self.billboard {
+            let Ok(mut draw) = self.glox.draw_builder(gl, camera) else {
+                return;
+            };
+            draw.bind_texture(Some(sprites.texture().raw()));
+            draw.draw_billboards(billboard, &instances);
+            draw.finish();
+        }
 
         // draw fps camera pos if orbital camera
         if self.chosen_camera == ChosenCamera::Orbital {
             let p = self.fps_camera.eye;
-            let mut draw = self.glox.draw_builder(gl, camera);
-            if let Some(atlas) = g.assets.get::<GGAtlas>("player") {
-                let texture = g.painter.texture(atlas.texture_id()).unwrap();
-                draw.bind_texture(texture.into());
+            if let Some(uv) = sprites.region("player") {
+                self.queue.push(
+                    DrawState {
+                        texture: Some(sprites.texture().raw()),
+                        depth: DepthState::read_only(),
+                        ..Default::default()
+                    },
+                    &glox::billboard_vertices(
+                        Vec3::new(p.x, p.y, 0.0),
+                        Vec4::splat(1.0),
+                        camera_dir,
+                        Vec2::splat(1.0),
+                        uv,
+                    ),
+                );
             }
-            draw.push_vertices(&glox::billboard_vertices(
-                Vec3::new(p.x, p.y, 0.0),
-                Vec4::splat(1.0),
-                camera_dir,
-                Vec2::splat(1.0),
-            ));
+
+            // and a line showing where it is looking
+            let Ok(mut draw) = self.glox.draw_builder(gl, camera) else {
+                return;
+            };
+            let color = Vec4::new(1.0, 1.0, 0.0, 1.0);
+            draw.topology(Topology::Lines);
+            draw.push_vertices(&[
+                Vertex::new(p, color, Vec2::ZERO),
+                Vertex::new(p + self.fps_camera.direction(), color, Vec2::ZERO),
+            ]);
             draw.finish();
         }
 
-        self.glox.swap();
+        // transparent draws go last, sorted back to front
+        if let Err(err) = self.queue.execute(&mut self.glox, gl, camera) {
+            eprintln!("failed to draw render queue: {err}");
+        }
+    }
+}
+
+/// Packs the sprite images into a single texture.
+fn sprite_atlas(glox: &mut Glox, gl: &glow::Context) -> Result<Atlas, GloxError> {
+    let mut builder = AtlasBuilder::new(2);
+    for name in ["cross", "lamp", "plant", "chairs", "player"] {
+        let options = ImageOptions {
+            premultiply: true,
+            ..Default::default()
+        };
+        let image = glox::decode_image_file(format!("examples/imgs/{name}_1x1.png"), options)?;
+        builder.add(name, image)?;
+    }
+    builder.build(glox, gl, TextureOptions::default())
+}
+
+/// Builds the walls and block tops of `MAP` into a single static mesh.
+fn level_mesh(gl: &glow::Context) -> Result<Mesh, GloxError> {
+    let mut walls = HashMap::new();
+    let size = MAP.len();
+
+    for y in 0..size {
+        for x in 0..size {
+            let cell = MAP[y][x];
+            if cell == 1 {
+                let x_i = x as i32;
+                let y_i = y as i32;
+
+                // Check adjacent cells before adding walls
+                let has_top = y > 0 && MAP[y - 1][x] == 1;
+                let has_right = x < size - 1 && MAP[y][x + 1] == 1;
+                let has_bottom = y < size - 1 && MAP[y + 1][x] == 1;
+                let has_left = x > 0 && MAP[y][x - 1] == 1;
+
+                if !has_top {
+                    walls.insert((x_i, y_i, true), ()); // top wall
+                }
+                if !has_right {
+                    walls.insert((x_i + 1, y_i, false), ()); // right wall
+                }
+                if !has_bottom {
+                    walls.insert((x_i, y_i + 1, true), ()); // bottom wall
+                }
+                if !has_left {
+                    walls.insert((x_i, y_i, false), ()); // left wall
+                }
+            }
+        }
+    }
+
+    let mut vertices = Vec::new();
+    let mut indices = Vec::new();
+    let mut push_quad = |(quad, quad_indices): ([LitVertex; 4], [u32; 6])| {
+        let base = vertices.len() as u32;
+        vertices.extend_from_slice(&quad);
+        indices.extend(quad_indices.iter().map(|i| i + base));
+    };
+
+    for (x, y, top) in walls.keys() {
+        let n = match top {
+            true => Vec3::new(0.0, 1.0, 0.0),
+            false => Vec3::new(1.0, 0.0, 0.0),
+        };
+        let color = Vec4::new(1.0, 1.0, 1.0, 1.0);
+        let p = match top {
+            true => Vec3::new(*x as f32 + 0.5, *y as f32, 0.0),
+            false => Vec3::new(*x as f32, *y as f32 + 0.5, 0.0),
+        };
+
+        push_quad(glox::wall_vertices_indexed(p, 1.0, color, n, UvRect::FULL));
+    }
+
+    // top of blocks
+    for y in 0..size {
+        for x in 0..size {
+            if MAP[y][x] != 1 {
+                continue;
+            }
+            let p = Vec3::new(x as f32 + 0.5, y as f32 + 0.5, 1.0);
+            let color = Vec4::new(0.2, 0.2, 0.2, 1.0);
+            push_quad(glox::floor_vertices_indexed(p, color, UvRect::FULL));
+        }
     }
+
+    Mesh::new(gl, &vertices, Some(&indices))
 }
 
 fn sat_test(pos1: &Vec3, half_size: f32, pos2: &Vec3, half_size2: f32) -> bool {
@@ -324,11 +415,7 @@ fn sat_test(pos1: &Vec3, half_size: f32, pos2: &Vec3, half_size2: f32) -> bool {
     let overlap_y = half_size + half_size2 - delta.y.abs();
     let overlap_z = half_size + half_size2 - delta.z.abs();
 
-    if overlap_x > 0.0 && overlap_y > 0.0 && overlap_z > 0.0 {
-        true
-    } else {
-        false
-    }
+    overlap_x > 0.0 && overlap_y > 0.0 && overlap_z > 0.0
 }
 
 fn collision_resolve(pos1: &mut Vec3, half_size1: f32, pos2: &Vec3, half_size2: f32) {
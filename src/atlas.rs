@@ -0,0 +1,277 @@
+use std::collections::HashMap;
+
+use glam::Vec2;
+
+use crate::{
+    Glox, GloxError, Image, TextureFormat, TextureHandle, TextureOptions, UvRect, check_len,
+};
+
+/// Packs many RGBA images into one texture so they can be drawn without rebinding.
+///
+/// Every image is surrounded by `padding` pixels copied from its edges, so filtering and
+/// mipmapping near the border of a region does not bleed in texels of its neighbours.
+#[derive(Clone, Debug)]
+pub struct AtlasBuilder {
+    padding: u32,
+    images: Vec<(String, Image)>,
+}
+
+impl Default for AtlasBuilder {
+    fn default() -> Self {
+        Self::new(1)
+    }
+}
+
+impl AtlasBuilder {
+    pub fn new(padding: u32) -> Self {
+        Self {
+            padding,
+            images: Vec::new(),
+        }
+    }
+
+    /// Adds `image` under `name`, replacing an earlier image with the same name. Fails if the
+    /// pixels do not match the size of the image.
+    pub fn add(&mut self, name: impl Into<String>, image: Image) -> Result<&mut Self, GloxError> {
+        check_len(TextureFormat::Rgba8, image.width, image.height, &image.pixels)?;
+        let name = name.into();
+        self.images.retain(|(existing, _)| *existing != name);
+        self.images.push((name, image));
+        Ok(self)
+    }
+
+    pub fn len(&self) -> usize {
+        self.images.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.images.is_empty()
+    }
+
+    /// Packs the images into a single power of two sized image, returning it together with
+    /// the region of every image.
+    pub fn pack(&self) -> (Image, HashMap<String, UvRect>) {
+        let padding = self.padding;
+        let padded = |image: &Image| (image.width + padding * 2, image.height + padding * 2);
+
+        // shelf packing, tallest images first so every shelf wastes little height
+        let mut order: Vec<&(String, Image)> = self.images.iter().collect();
+        order.sort_by_key(|(_, image)| std::cmp::Reverse(image.height));
+
+        let area: u64 = order
+            .iter()
+            .map(|(_, image)| {
+                let (w, h) = padded(image);
+                w as u64 * h as u64
+            })
+            .sum();
+        let widest = order.iter().map(|(_, image)| padded(image).0).max().unwrap_or(1);
+        let width = ((area as f64).sqrt().ceil() as u32).max(widest).next_power_of_two();
+
+        let mut placements = Vec::with_capacity(order.len());
+        let (mut x, mut y, mut shelf_height) = (0, 0, 0);
+        for (name, image) in order {
+            let (w, h) = padded(image);
+            if x + w > width {
+                x = 0;
+                y += shelf_height;
+                shelf_height = 0;
+            }
+            placements.push((name, image, x + padding, y + padding));
+            x += w;
+            shelf_height = shelf_height.max(h);
+        }
+        let height = (y + shelf_height).max(1).next_power_of_two();
+
+        let mut atlas = Image {
+            width,
+            height,
+            pixels: vec![0; width as usize * height as usize * 4],
+        };
+        let size = Vec2::new(width as f32, height as f32);
+        let mut regions = HashMap::with_capacity(placements.len());
+        for (name, image, x, y) in placements {
+            blit_extruded(&mut atlas, image, x, y, padding);
+            let min = Vec2::new(x as f32, y as f32);
+            let max = min + Vec2::new(image.width as f32, image.height as f32);
+            regions.insert(name.clone(), UvRect::new(min / size, max / size));
+        }
+        (atlas, regions)
+    }
+
+    /// Packs the images and uploads them as one texture.
+    pub fn build(
+        &self,
+        glox: &mut Glox,
+        gl: &glow::Context,
+        options: TextureOptions,
+    ) -> Result<Atlas, GloxError> {
+        let (image, regions) = self.pack();
+        let texture = glox.create_texture_from_image(gl, &image, options)?;
+        Ok(Atlas { texture, regions })
+    }
+}
+
+/// Copies `image` to `x`, `y` in `atlas`, repeating its edge pixels `padding` times outwards.
+fn blit_extruded(atlas: &mut Image, image: &Image, x: u32, y: u32, padding: u32) {
+    if image.width == 0 || image.height == 0 {
+        return;
+    }
+    let padding = padding as i64;
+    for dy in -padding..image.height as i64 + padding {
+        let src_y = dy.clamp(0, image.height as i64 - 1) as usize;
+        let dst_y = (y as i64 + dy) as usize;
+        for dx in -padding..image.width as i64 + padding {
+            let src_x = dx.clamp(0, image.width as i64 - 1) as usize;
+            let dst_x = (x as i64 + dx) as usize;
+            let src = (src_y * image.width as usize + src_x) * 4;
+            let dst = (dst_y * atlas.width as usize + dst_x) * 4;
+            atlas.pixels[dst..dst + 4].copy_from_slice(&image.pixels[src..src + 4]);
+        }
+    }
+}
+
+/// A texture holding many named images, created with `AtlasBuilder::build`. The texture must be
+/// freed with `Atlas::destroy`.
+#[derive(Debug)]
+pub struct Atlas {
+    texture: TextureHandle,
+    regions: HashMap<String, UvRect>,
+}
+
+impl Atlas {
+    pub fn texture(&self) -> &TextureHandle {
+        &self.texture
+    }
+
+    /// The region of the image added as `name`.
+    pub fn region(&self, name: &str) -> Option<UvRect> {
+        self.regions.get(name).copied()
+    }
+
+    pub fn regions(&self) -> &HashMap<String, UvRect> {
+        &self.regions
+    }
+
+    pub fn destroy(self, glox: &mut Glox, gl: &glow::Context) {
+        glox.delete_texture(gl, self.texture);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn solid(width: u32, height: u32, color: [u8; 4]) -> Image {
+        Image {
+            width,
+            height,
+            pixels: color.repeat((width * height) as usize),
+        }
+    }
+
+    fn pixel(image: &Image, x: u32, y: u32) -> [u8; 4] {
+        let i = (y * image.width + x) as usize * 4;
+        image.pixels[i..i + 4].try_into().unwrap()
+    }
+
+    #[test]
+    fn add_rejects_inconsistent_image() {
+        let image = Image {
+            width: 2,
+            height: 2,
+            pixels: vec![0; 4],
+        };
+        let result = AtlasBuilder::new(1).add("broken", image).map(|_| ());
+        assert_eq!(
+            result,
+            Err(GloxError::InvalidTextureData {
+                expected: 16,
+                actual: 4
+            })
+        );
+    }
+
+    #[test]
+    fn pack_empty() {
+        let (image, regions) = AtlasBuilder::new(1).pack();
+        assert_eq!((image.width, image.height), (1, 1));
+        assert!(regions.is_empty());
+    }
+
+    #[test]
+    fn pack_places_images_without_overlap() {
+        let mut builder = AtlasBuilder::new(2);
+        builder.add("a", solid(8, 4, [255, 0, 0, 255])).unwrap();
+        builder.add("b", solid(3, 6, [0, 255, 0, 255])).unwrap();
+        builder.add("c", solid(5, 5, [0, 0, 255, 255])).unwrap();
+        let (image, regions) = builder.pack();
+
+        assert!(image.width.is_power_of_two() && image.height.is_power_of_two());
+        assert_eq!(image.pixels.len(), (image.width * image.height * 4) as usize);
+        assert_eq!(regions.len(), 3);
+
+        let size = Vec2::new(image.width as f32, image.height as f32);
+        let texels = |name: &str| {
+            let region = regions[name];
+            ((region.min * size).round(), (region.max * size).round())
+        };
+        for (name, width, height, color) in [
+            ("a", 8.0, 4.0, [255, 0, 0, 255]),
+            ("b", 3.0, 6.0, [0, 255, 0, 255]),
+            ("c", 5.0, 5.0, [0, 0, 255, 255]),
+        ] {
+            let (min, max) = texels(name);
+            assert_eq!(max - min, Vec2::new(width, height), "{name}");
+            // padding keeps regions off the border of the atlas
+            assert!(min.x >= 2.0 && min.y >= 2.0, "{name}");
+            assert!(max.x + 2.0 <= size.x && max.y + 2.0 <= size.y, "{name}");
+            for y in min.y as u32..max.y as u32 {
+                for x in min.x as u32..max.x as u32 {
+                    assert_eq!(pixel(&image, x, y), color, "{name} at {x}, {y}");
+                }
+            }
+        }
+
+        // regions including their padding must not overlap
+        let names = ["a", "b", "c"];
+        for (i, a) in names.iter().enumerate() {
+            for b in &names[i + 1..] {
+                let (a_min, a_max) = texels(a);
+                let (b_min, b_max) = texels(b);
+                let separate = a_max.x + 2.0 <= b_min.x - 2.0
+                    || b_max.x + 2.0 <= a_min.x - 2.0
+                    || a_max.y + 2.0 <= b_min.y - 2.0
+                    || b_max.y + 2.0 <= a_min.y - 2.0;
+                assert!(separate, "{a} and {b} overlap");
+            }
+        }
+    }
+
+    #[test]
+    fn pack_extrudes_edges() {
+        // a 2x2 image with a different color per pixel
+        let image = Image {
+            width: 2,
+            height: 2,
+            pixels: [[1, 0, 0, 255], [2, 0, 0, 255], [3, 0, 0, 255], [4, 0, 0, 255]].concat(),
+        };
+        let mut builder = AtlasBuilder::new(2);
+        builder.add("image", image.clone()).unwrap();
+        let (atlas, regions) = builder.pack();
+        assert_eq!((atlas.width, atlas.height), (8, 8));
+        assert_eq!(regions["image"], UvRect::new(Vec2::splat(0.25), Vec2::splat(0.5)));
+
+        // every atlas pixel of the padded block repeats the nearest image pixel
+        for y in 0..6 {
+            for x in 0..6 {
+                let src_x = x.clamp(2, 3) - 2;
+                let src_y = y.clamp(2, 3) - 2;
+                assert_eq!(pixel(&atlas, x, y), pixel(&image, src_x, src_y), "at {x}, {y}");
+            }
+        }
+        // outside the padding the atlas stays empty
+        assert_eq!(pixel(&atlas, 6, 0), [0; 4]);
+        assert_eq!(pixel(&atlas, 0, 6), [0; 4]);
+    }
+}
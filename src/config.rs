@@ -0,0 +1,49 @@
+use glam::Vec4;
+
+use crate::{BlendMode, CullMode, DepthState};
+
+/// Settings used by `Glox::init` to create its GL objects and by draws as their default state.
+#[derive(Clone, Debug, PartialEq)]
+pub struct GloxConfig {
+    /// Number of `Vertex`es each streaming vertex buffer holds before it has to grow. Other
+    /// vertex layouts share the same number of bytes.
+    pub vertex_capacity: usize,
+    /// Number of `u32` indices each streaming index buffer holds before it has to grow.
+    pub index_capacity: usize,
+    /// Number of instances each streaming instance buffer holds before it has to grow.
+    pub instance_capacity: usize,
+    /// Number of streaming buffers of each kind rotated by `Glox::end_frame`, i.e. frames that can be in flight.
+    pub buffer_count: usize,
+    /// Default clear color of frames, see `FrameDesc::from_config`.
+    pub clear_color: Vec4,
+    /// Blend mode draws start with, see `DrawBuilder::blend_mode`.
+    pub blend_mode: BlendMode,
+    /// Depth state draws start with, `None` leaves it to the caller. See `DrawBuilder::depth_state`.
+    pub depth: Option<DepthState>,
+    /// Cull mode draws start with, see `DrawBuilder::cull_mode`.
+    pub cull_mode: CullMode,
+    /// Replaces the `#version` line prepended to the shaders, e.g. `"#version 300 es"`.
+    pub shader_version: Option<String>,
+    /// Whether `Glox::begin` and `Glox::end` save and restore the GL state glox changes, so
+    /// glox can draw inside a host renderer such as egui. Apps owning the whole context can
+    /// turn this off to skip the state queries. See `Glox::begin` for what is saved.
+    /// `UNPACK_ALIGNMENT`, changed by texture uploads, is restored by the uploads themselves.
+    pub preserve_gl_state: bool,
+}
+
+impl Default for GloxConfig {
+    fn default() -> Self {
+        Self {
+            vertex_capacity: 1024 * 1024, // 1 million vertices
+            index_capacity: 1024 * 1024,
+            instance_capacity: 64 * 1024,
+            buffer_count: 3,
+            clear_color: Vec4::new(0.0, 0.0, 0.0, 1.0),
+            blend_mode: BlendMode::default(),
+            depth: None,
+            cull_mode: CullMode::None,
+            shader_version: None,
+            preserve_gl_state: true,
+        }
+    }
+}
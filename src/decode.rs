@@ -0,0 +1,168 @@
+use std::path::Path;
+
+use crate::{Glox, GloxError, Image, TextureHandle, TextureOptions};
+
+/// Image file formats that can be decoded.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum ImageFormat {
+    Png,
+    Tga,
+    Bmp,
+}
+
+impl ImageFormat {
+    /// The format of files ending in `extension`, ignoring case.
+    pub fn from_extension(extension: &str) -> Option<Self> {
+        match extension.to_ascii_lowercase().as_str() {
+            "png" => Some(Self::Png),
+            "tga" => Some(Self::Tga),
+            "bmp" => Some(Self::Bmp),
+            _ => None,
+        }
+    }
+
+    fn to_image(self) -> image::ImageFormat {
+        match self {
+            Self::Png => image::ImageFormat::Png,
+            Self::Tga => image::ImageFormat::Tga,
+            Self::Bmp => image::ImageFormat::Bmp,
+        }
+    }
+}
+
+/// Options for decoding images with `decode_image` and `Glox::load_texture`.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct ImageOptions {
+    /// The format of the data. `None` detects PNG and BMP from their signature and otherwise
+    /// assumes TGA, which has none. `decode_image_file` uses the file extension if not set.
+    pub format: Option<ImageFormat>,
+    /// Multiplies colors by alpha after decoding, see `Image::premultiply`.
+    pub premultiply: bool,
+    /// Pixels of this color become fully transparent, see `Image::apply_color_key`.
+    pub color_key: Option<[u8; 3]>,
+}
+
+/// Decodes a PNG, TGA or BMP image from memory into RGBA8 pixels.
+pub fn decode_image(bytes: &[u8], options: ImageOptions) -> Result<Image, GloxError> {
+    let format = match options.format {
+        Some(format) => format.to_image(),
+        None => image::guess_format(bytes).unwrap_or(image::ImageFormat::Tga),
+    };
+    let decoded = image::load_from_memory_with_format(bytes, format)
+        .map_err(|err| GloxError::ImageDecode(err.to_string()))?
+        .into_rgba8();
+    let mut image = Image {
+        width: decoded.width(),
+        height: decoded.height(),
+        pixels: decoded.into_raw(),
+    };
+    if let Some(key) = options.color_key {
+        image.apply_color_key(key);
+    }
+    if options.premultiply {
+        image.premultiply();
+    }
+    Ok(image)
+}
+
+/// Reads and decodes a PNG, TGA or BMP file into RGBA8 pixels.
+pub fn decode_image_file(
+    path: impl AsRef<Path>,
+    options: ImageOptions,
+) -> Result<Image, GloxError> {
+    let path = path.as_ref();
+    let format = options.format.or_else(|| {
+        let extension = path.extension()?.to_str()?;
+        ImageFormat::from_extension(extension)
+    });
+    let bytes = std::fs::read(path).map_err(|err| GloxError::ImageDecode(err.to_string()))?;
+    decode_image(&bytes, ImageOptions { format, ..options })
+}
+
+impl Glox {
+    /// Decodes an image from memory and uploads it as a texture.
+    pub fn load_texture(
+        &mut self,
+        gl: &glow::Context,
+        bytes: &[u8],
+        image_options: ImageOptions,
+        options: TextureOptions,
+    ) -> Result<TextureHandle, GloxError> {
+        let image = decode_image(bytes, image_options)?;
+        self.create_texture_from_image(gl, &image, options)
+    }
+
+    /// Reads and decodes an image file and uploads it as a texture.
+    pub fn load_texture_file(
+        &mut self,
+        gl: &glow::Context,
+        path: impl AsRef<Path>,
+        image_options: ImageOptions,
+        options: TextureOptions,
+    ) -> Result<TextureHandle, GloxError> {
+        let image = decode_image_file(path, image_options)?;
+        self.create_texture_from_image(gl, &image, options)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A 2x2 image with a different color per pixel.
+    fn rgba() -> image::RgbaImage {
+        let pixels = [[255, 0, 0, 255], [0, 255, 0, 255], [0, 0, 255, 255], [10, 20, 30, 255]];
+        image::RgbaImage::from_raw(2, 2, pixels.concat()).unwrap()
+    }
+
+    fn encode(format: ImageFormat) -> Vec<u8> {
+        let mut bytes = std::io::Cursor::new(Vec::new());
+        rgba().write_to(&mut bytes, format.to_image()).unwrap();
+        bytes.into_inner()
+    }
+
+    fn assert_round_trip(format: ImageFormat) {
+        let bytes = encode(format);
+        let expected = rgba().into_raw();
+        for options in [
+            ImageOptions::default(),
+            ImageOptions {
+                format: Some(format),
+                ..Default::default()
+            },
+        ] {
+            let image = decode_image(&bytes, options).unwrap();
+            assert_eq!((image.width, image.height), (2, 2), "{format:?}");
+            assert_eq!(image.pixels, expected, "{format:?}");
+        }
+
+        let extension = format!("{format:?}").to_ascii_lowercase();
+        let path = std::env::temp_dir()
+            .join(format!("glox-decode-{}-{extension}.{extension}", std::process::id()));
+        std::fs::write(&path, &bytes).unwrap();
+        let image = decode_image_file(&path, ImageOptions::default());
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(image.unwrap().pixels, expected, "{format:?} file");
+    }
+
+    #[test]
+    fn png_round_trip() {
+        assert_round_trip(ImageFormat::Png);
+    }
+
+    #[test]
+    fn tga_round_trip() {
+        assert_round_trip(ImageFormat::Tga);
+    }
+
+    #[test]
+    fn bmp_round_trip() {
+        assert_round_trip(ImageFormat::Bmp);
+    }
+
+    #[test]
+    fn from_extension() {
+        assert_eq!(ImageFormat::from_extension("TGA"), Some(ImageFormat::Tga));
+        assert_eq!(ImageFormat::from_extension("jpg"), None);
+    }
+}
@@ -1,110 +1,518 @@
+use std::marker::PhantomData;
+use std::sync::LazyLock;
+
+use glam::{Mat3, Mat4, Vec3};
 use glow::HasContext as _;
 
-use crate::{Camera, Glox, Vertex};
+use crate::{
+    BlendMode, Camera, CullMode, DepthState, Glox, GloxError, Instance, Lights,
+    MAX_POINT_LIGHTS, Mesh, ProgramHandle, Topology, UniformValue, Vertex, VertexLayout,
+    attrib_pointers, cast_slice, disable_attribs,
+};
+
+/// Uniform names of the position, color, linear and quadratic of each point light, built once.
+static POINT_LIGHT_UNIFORMS: LazyLock<[[String; 4]; MAX_POINT_LIGHTS]> = LazyLock::new(|| {
+    std::array::from_fn(|i| {
+        ["position", "color", "linear", "quadratic"].map(|field| format!("point_lights[{i}].{field}"))
+    })
+});
 
-pub struct DrawBuilder<'a> {
+/// Streams vertices of layout `V` and draws them with as few draw calls as the state allows.
+pub struct DrawBuilder<'a, V: VertexLayout = Vertex> {
     renderer: &'a mut Glox,
     gl: &'a glow::Context,
+    camera: &'a dyn Camera,
+    program: glow::Program,
+    vertex_array: glow::VertexArray,
+    texture: Option<glow::Texture>,
+    topology: Topology,
+    blend_mode: BlendMode,
+    depth: Option<DepthState>,
+    cull_mode: CullMode,
+    model: Mat4,
+    point_size: f32,
+    lights: Option<Lights>,
     first: usize,
     count: usize,
+    index_first: usize,
+    index_count: usize,
+    layout: PhantomData<V>,
 }
 
-impl<'a> DrawBuilder<'a> {
-    const fn stride() -> i32 {
-        std::mem::size_of::<Vertex>() as i32
+impl<'a, V: VertexLayout> DrawBuilder<'a, V> {
+    /// Binds `texture` for the draw, or the shared white texture if `None`.
+    /// Anything pushed before is drawn with the previous texture.
+    pub fn bind_texture(&mut self, texture: Option<glow::Texture>) -> &mut Self {
+        let texture = texture.or(self.renderer.white_texture);
+        if self.texture != texture {
+            self.flush();
+            self.texture = texture;
+            self.renderer.state.bind_texture(self.gl, texture);
+        }
+        self
     }
 
-    pub fn bind_texture(&mut self, texture: Option<glow::Texture>) -> &mut Self {
-        unsafe {
-            self.gl.bind_texture(glow::TEXTURE_2D, texture);
+    /// Sets how pushed vertices are assembled into primitives. Defaults to `Topology::Triangles`.
+    /// Anything pushed before is drawn with the previous topology.
+    pub fn topology(&mut self, topology: Topology) -> &mut Self {
+        if self.topology != topology {
+            self.flush();
+            self.topology = topology;
         }
         self
     }
 
-    pub fn new(renderer: &'a mut Glox, gl: &'a glow::Context, camera:&'a dyn Camera) -> Self {
-        unsafe {
-            let program = renderer.program.expect("no program");
-            let vertex_buffer = renderer.vertex_buffers[renderer.vertex_buffer_current];
-            let vertex_array = renderer.vertex_array.expect("no vertex_array");
-            let first = renderer.vertex_buffer_vertex_index;
-            gl.bind_vertex_array(Some(vertex_array));
-            let texture = gl.create_texture().expect("failed to create texture");
-            gl.bind_texture(glow::TEXTURE_2D, Some(texture));
-            let data = vec![255u8, 255u8, 255u8, 255u8]; // white texture
-            gl.tex_image_2d(
-                glow::TEXTURE_2D,
-                0,
-                glow::RGBA as i32,
-                1,
-                1,
-                0,
-                glow::RGBA,
-                glow::UNSIGNED_BYTE,
-                glow::PixelUnpackData::Slice(Some(&data)),
-            );
-            gl.generate_mipmap(glow::TEXTURE_2D);
-            gl.bind_buffer(glow::ARRAY_BUFFER, Some(vertex_buffer));
-            gl.use_program(Some(program));
-            let view_projection = camera.view_projection();
-            gl.uniform_matrix_4_f32_slice(
-                gl.get_uniform_location(program, "view_projection").as_ref(),
-                false,
-                view_projection.as_ref(),
-            );
-
-            gl.enable_vertex_attrib_array(0);
-            gl.vertex_attrib_pointer_f32(0, 3, glow::FLOAT, false, Self::stride(), 0);
-            let offset = 3 * std::mem::size_of::<f32>() as i32;
-
-            gl.enable_vertex_attrib_array(1);
-            gl.vertex_attrib_pointer_f32(1, 4, glow::FLOAT, false, Self::stride(), offset);
-            let offset = offset + 4 * std::mem::size_of::<f32>() as i32;
-
-            gl.enable_vertex_attrib_array(2);
-            gl.vertex_attrib_pointer_f32(2, 2, glow::FLOAT, false, Self::stride(), offset);
-
-            Self {
-                renderer,
-                gl,
-                first,
-                count: 0,
-            }
+    /// Sets how the draw is blended with the framebuffer. Defaults to `GloxConfig::blend_mode`.
+    /// Anything pushed before is drawn with the previous blend mode.
+    pub fn blend_mode(&mut self, blend_mode: BlendMode) -> &mut Self {
+        if self.blend_mode != blend_mode {
+            self.flush();
+            self.blend_mode = blend_mode;
         }
+        self
     }
-    
-    pub fn push_vertices(&mut self, vertices: &[Vertex]) -> &mut Self {
-        if self.renderer.vertex_buffer_vertex_index + vertices.len()
-            >= self.renderer.vertex_buffer_len
+
+    /// Sets the depth test and depth write state. Defaults to `GloxConfig::depth`.
+    /// Anything pushed before is drawn with the previous depth state.
+    pub fn depth_state(&mut self, depth: DepthState) -> &mut Self {
+        if self.depth != Some(depth) {
+            self.flush();
+            self.depth = Some(depth);
+        }
+        self
+    }
+
+    /// Sets which faces are culled. Defaults to `GloxConfig::cull_mode`.
+    /// Anything pushed before is drawn with the previous cull mode.
+    pub fn cull_mode(&mut self, cull_mode: CullMode) -> &mut Self {
+        if self.cull_mode != cull_mode {
+            self.flush();
+            self.cull_mode = cull_mode;
+        }
+        self
+    }
+
+    /// Sets the size in pixels of points drawn with `Topology::Points`. Defaults to 1.0.
+    pub fn point_size(&mut self, size: f32) -> &mut Self {
+        self.flush();
+        self.point_size = size;
+        self.set_uniform("point_size", size)
+    }
+
+    /// Draws subsequently pushed vertices with `program`, or the built-in program if `None`.
+    /// Anything pushed before is drawn with the previous program.
+    pub fn use_program(&mut self, program: Option<&ProgramHandle>) -> &mut Self {
+        let Some(program) = program.map(ProgramHandle::raw).or(self.builtin_program()) else {
+            return self;
+        };
+        self.switch_program(program)
+    }
+
+    /// The built-in program matching whether lights are set.
+    fn builtin_program(&self) -> Option<glow::Program> {
+        match self.lights {
+            Some(_) => self.renderer.lit_program,
+            None => self.renderer.program,
+        }
+    }
+
+    fn switch_program(&mut self, program: glow::Program) -> &mut Self {
+        if self.program != program {
+            self.flush();
+            self.program = program;
+            self.renderer.state.use_program(self.gl, program);
+            self.upload_uniforms();
+        }
+        self
+    }
+
+    /// Lights subsequently pushed vertices and meshes with `lights`, switching the built-in
+    /// program to the lit one, or turns lighting off if `None`. Only vertices with a normal, like
+    /// `LitVertex`, are lit. Custom programs receive the light uniforms if they declare them.
+    /// Anything pushed before is drawn with the previous lights.
+    pub fn lights(&mut self, lights: Option<&Lights>) -> &mut Self {
+        self.flush();
+        self.lights = lights.cloned();
+        let builtin = [self.renderer.program, self.renderer.lit_program];
+        if builtin.contains(&Some(self.program))
+            && let Some(program) = self.builtin_program()
+            && program != self.program
         {
-            self.renderer.vertex_buffer_vertex_index = 0;
+            return self.switch_program(program);
         }
+        self.upload_lights();
+        self
+    }
 
-        unsafe {
-            let vertex_data = std::slice::from_raw_parts(
-                vertices.as_ptr() as *const u8,
-                std::mem::size_of_val(vertices),
-            );
+    fn upload_lights(&mut self) {
+        // taken out while uploading, as `upload` borrows the whole builder
+        let Some(lights) = self.lights.take() else {
+            return;
+        };
+        self.upload("ambient", lights.ambient);
+        let (direction, color) = match lights.directional {
+            Some(light) => (light.direction.normalize_or_zero(), light.color),
+            None => (Vec3::NEG_Z, Vec3::ZERO),
+        };
+        self.upload("light_direction", direction);
+        self.upload("light_color", color);
+        let point_lights = &lights.point_lights[..lights.point_lights.len().min(MAX_POINT_LIGHTS)];
+        self.upload("point_light_count", point_lights.len() as i32);
+        for (light, [position, color, linear, quadratic]) in
+            point_lights.iter().zip(POINT_LIGHT_UNIFORMS.iter())
+        {
+            self.upload(position, light.position);
+            self.upload(color, light.color);
+            self.upload(linear, light.linear);
+            self.upload(quadratic, light.quadratic);
+        }
+        self.lights = Some(lights);
+    }
+
+    /// Sets the uniform `name` of the current program. Uniforms the program does not declare
+    /// are ignored. Anything pushed before is drawn with the previous value.
+    pub fn set_uniform<T: UniformValue>(&mut self, name: &str, value: T) -> &mut Self {
+        self.flush();
+        self.upload(name, value);
+        self
+    }
+
+    fn upload<T: UniformValue>(&mut self, name: &str, value: T) {
+        let location = self.renderer.uniforms.location(self.gl, self.program, name);
+        value.upload(self.gl, location.as_ref());
+    }
 
-            self.gl.buffer_sub_data_u8_slice(
-                glow::ARRAY_BUFFER,
-                self.renderer.vertex_buffer_vertex_index as i32 * Self::stride(),
-                vertex_data,
-            );
-            let count = vertices.len();
-            self.renderer.vertex_buffer_vertex_index += count as usize;
-            self.count += count;
+    /// Binds `texture` to texture `unit` and points the sampler uniform `name` of the current
+    /// program at it. Unit 0 is the texture set with `bind_texture`, which is what binding to
+    /// unit 0 here does, so use 1 and up for additional textures.
+    pub fn set_sampler(
+        &mut self,
+        name: &str,
+        unit: u32,
+        texture: Option<glow::Texture>,
+    ) -> &mut Self {
+        if unit == 0 {
+            self.bind_texture(texture);
+            return self.set_uniform(name, 0);
         }
+        self.flush();
+        self.renderer.save_texture_unit(self.gl, unit);
+        self.renderer.state.bind_texture_unit(self.gl, unit, texture);
+        self.set_uniform(name, unit as i32)
+    }
+
+    /// Uploads the uniforms the builder manages to the current program.
+    fn upload_uniforms(&mut self) {
+        self.upload("view_projection", self.camera.view_projection());
+        self.upload_model(self.model);
+        self.upload("point_size", self.point_size);
+        self.upload_lights();
+    }
 
+    /// Sets the model matrix applied to subsequently pushed vertices on the GPU.
+    /// Anything pushed before is drawn with the previous model matrix.
+    pub fn set_model(&mut self, model: Mat4) -> &mut Self {
+        if self.model != model {
+            self.flush();
+            self.model = model;
+            self.upload_model(model);
+        }
         self
     }
 
-    pub fn finish(self) {
+    /// Uploads `model` along with the matrix the lit program transforms normals by, computed
+    /// here once rather than per vertex.
+    fn upload_model(&mut self, model: Mat4) {
+        self.upload("model", model);
+        self.upload("normal_matrix", Mat3::from_mat4(model).inverse().transpose());
+    }
+
+    pub fn new(renderer: &'a mut Glox, gl: &'a glow::Context, camera:&'a dyn Camera) -> Result<Self, GloxError> {
+        debug_assert!(renderer.in_frame, "glox: drawing outside of begin_frame and end_frame");
+        let Some(program) = renderer.program else {
+            return Err(GloxError::NotInitialized);
+        };
+        let Some(white_texture) = renderer.white_texture else {
+            return Err(GloxError::NotInitialized);
+        };
+        let slot = renderer.vertex_stream.current_index();
+        let Some(&vertex_array) = renderer.stream_vertex_arrays::<V>(gl)?.get(slot) else {
+            return Err(GloxError::NotInitialized);
+        };
+        // the vertex array must be bound before the index stream touches its element array binding
+        renderer.state.bind_vertex_array(gl, vertex_array);
+        renderer.sync_slot(gl);
+        renderer.vertex_stream.ensure_capacity(gl);
+        renderer.index_stream.ensure_capacity(gl);
+        renderer.instance_stream.ensure_capacity(gl);
+        renderer.state.bind_texture(gl, Some(white_texture));
+        renderer.state.use_program(gl, program);
+        let blend_mode = renderer.config.blend_mode;
+        let depth = renderer.config.depth;
+        let cull_mode = renderer.config.cull_mode;
+        let first = renderer.vertex_stream.cursor().div_ceil(size_of::<V>());
+        let index_first = renderer.index_stream.cursor();
+
+        let mut draw = Self {
+            renderer,
+            gl,
+            camera,
+            program,
+            vertex_array,
+            texture: Some(white_texture),
+            topology: Topology::default(),
+            blend_mode,
+            depth,
+            cull_mode,
+            model: Mat4::IDENTITY,
+            point_size: 1.0,
+            lights: None,
+            first,
+            count: 0,
+            index_first,
+            index_count: 0,
+            layout: PhantomData,
+        };
+        draw.upload_uniforms();
+        Ok(draw)
+    }
+    
+    /// Makes room for `vertices` and `indices` more elements, flushing what has been pushed so
+    /// far and growing the streaming buffers if they would not fit.
+    fn reserve(&mut self, vertices: usize, indices: usize) {
+        // the vertex stream is addressed in bytes and may need aligning to the vertex size
+        let vertex_bytes = vertices * size_of::<V>() + size_of::<V>() - 1;
+        let vertices_fit = self.renderer.vertex_stream.fits(vertex_bytes);
+        let indices_fit = self.renderer.index_stream.fits(indices);
+        if vertices_fit && indices_fit {
+            return;
+        }
+
+        // draw what we have and continue in a fresh range of a larger buffer
+        if self.count > 0 || self.index_count > 0 {
+            self.flush();
+            self.renderer.frame_stats.flushes += 1;
+        }
+        if !vertices_fit {
+            self.renderer.vertex_stream.grow(self.gl, vertex_bytes);
+        }
+        if !indices_fit {
+            self.renderer.index_stream.grow(self.gl, indices);
+        }
+        self.first = self.vertex_cursor();
+        self.index_first = self.renderer.index_stream.cursor();
+    }
+
+    /// Index of the vertex the next write will land at.
+    fn vertex_cursor(&self) -> usize {
+        self.renderer.vertex_stream.cursor().div_ceil(size_of::<V>())
+    }
+
+    /// Writes `vertices` to the vertex stream and returns the index of the first one.
+    fn write_vertices(&mut self, vertices: &[V]) -> usize {
+        let stream = &mut self.renderer.vertex_stream;
+        stream.align(size_of::<V>());
+        stream.write(self.gl, cast_slice(vertices)) / size_of::<V>()
+    }
+
+    pub fn push_vertices(&mut self, vertices: &[V]) -> &mut Self {
+        // indexed vertices pushed before would end up in the range drawn here, and must be
+        // drawn first anyway to keep the order
+        if self.index_count > 0 {
+            self.flush();
+        }
+        self.reserve(vertices.len(), 0);
+        self.write_vertices(vertices);
+        self.count += vertices.len();
+
+        self
+    }
+
+    /// Pushes indexed triangles. `indices` are relative to `vertices` and are rebased to where
+    /// the vertices end up in the streaming buffer.
+    pub fn push_indexed(&mut self, vertices: &[V], indices: &[u32]) -> &mut Self {
+        // the vertices written here would end up in the range of pending non-indexed vertices
+        if self.count > 0 {
+            self.flush();
+        }
+        self.reserve(vertices.len(), indices.len());
+        let base = self.write_vertices(vertices) as u32;
+        let indices: Vec<u32> = indices.iter().map(|index| index + base).collect();
+        self.renderer.index_stream.write(self.gl, cast_slice(&indices));
+        self.index_count += indices.len();
+
+        self
+    }
+
+    /// Draws `mesh` with its vertices transformed by `model` in place of the model matrix set
+    /// with `set_model`, using the current texture and topology. Anything pushed before is drawn
+    /// first.
+    pub fn draw_mesh(&mut self, mesh: &Mesh, model: Mat4) -> &mut Self {
+        self.flush();
+        let Some(vertex_array) = mesh.vertex_array() else {
+            return self;
+        };
+        self.upload_model(model);
+        self.apply_state();
+        self.renderer.state.bind_vertex_array(self.gl, vertex_array);
+        unsafe {
+            let mode = self.topology.to_gl();
+            if mesh.is_indexed() {
+                self.gl
+                    .draw_elements(mode, mesh.count() as i32, glow::UNSIGNED_INT, 0);
+            } else {
+                self.gl.draw_arrays(mode, 0, mesh.count() as i32);
+            }
+        }
+        self.record_draw(mesh.is_indexed(), mesh.count());
+        self.renderer.state.bind_vertex_array(self.gl, self.vertex_array);
+        self.upload_model(self.model);
+        self
+    }
+
+    /// Draws `mesh` once per instance, offset, scaled and tinted by each instance.
+    /// Anything pushed before is drawn first.
+    pub fn draw_instances(&mut self, mesh: &Mesh, instances: &[Instance]) -> &mut Self {
+        self.draw_instanced(mesh, instances, false)
+    }
+
+    /// Like `draw_instances`, but rotates each instance around the z axis to face the camera,
+    /// as `billboard_vertices` does. Use `Mesh::billboard` as the base mesh.
+    pub fn draw_billboards(&mut self, mesh: &Mesh, instances: &[Instance]) -> &mut Self {
+        self.draw_instanced(mesh, instances, true)
+    }
+
+    fn draw_instanced(&mut self, mesh: &Mesh, instances: &[Instance], billboard: bool) -> &mut Self {
+        self.flush();
+        let (Some(vertex_array), Some(program)) =
+            (mesh.vertex_array(), self.renderer.instanced_program)
+        else {
+            return self;
+        };
+        if instances.is_empty() {
+            return self;
+        }
+
+        let stream = &mut self.renderer.instance_stream;
+        if !stream.fits(instances.len()) {
+            stream.grow(self.gl, instances.len());
+        }
+        // leaves the instance buffer bound to ARRAY_BUFFER for the attribute pointers below
+        let first = stream.write(self.gl, cast_slice(instances));
+        self.apply_state();
+        let builder_program = self.program;
+        self.program = program;
+        self.renderer.state.use_program(self.gl, program);
+        self.upload("view_projection", self.camera.view_projection());
+        self.upload("view", self.camera.view());
+        self.upload("model", self.model);
+        self.upload("billboard", billboard as i32);
+        self.program = builder_program;
+
+        self.renderer.state.bind_vertex_array(self.gl, vertex_array);
+        attrib_pointers::<Instance>(self.gl, first * size_of::<Instance>(), 1);
         unsafe {
-            self.gl.blend_func(glow::ONE, glow::ONE_MINUS_SRC_ALPHA);
-            self.gl.enable(glow::BLEND);
+            let mode = self.topology.to_gl();
+            let instance_count = instances.len() as i32;
+            if mesh.is_indexed() {
+                self.gl.draw_elements_instanced(
+                    mode,
+                    mesh.count() as i32,
+                    glow::UNSIGNED_INT,
+                    0,
+                    instance_count,
+                );
+            } else {
+                self.gl
+                    .draw_arrays_instanced(mode, 0, mesh.count() as i32, instance_count);
+            }
+        }
+        self.record_draw(mesh.is_indexed(), mesh.count() * instances.len());
+        disable_attribs::<Instance>(self.gl);
+        self.renderer.state.bind_vertex_array(self.gl, self.vertex_array);
+        self.renderer.state.use_program(self.gl, self.program);
+        self
+    }
 
-            self.gl
-                .draw_arrays(glow::TRIANGLES, self.first as i32, self.count as i32);
+    /// Counts a draw call of `count` vertices or indices in the frame statistics.
+    fn record_draw(&mut self, indexed: bool, count: usize) {
+        let stats = &mut self.renderer.frame_stats;
+        stats.draw_calls += 1;
+        if indexed {
+            stats.indices += count;
+        } else {
+            stats.vertices += count;
         }
     }
+
+    fn apply_state(&mut self) {
+        let (gl, state) = (self.gl, &mut self.renderer.state);
+        match self.blend_mode.to_gl() {
+            Some((src, dst, equation)) => {
+                state.blend(gl, src, dst, equation);
+                state.set_enabled(gl, glow::BLEND, true);
+            }
+            None => state.set_enabled(gl, glow::BLEND, false),
+        }
+        if let Some(depth) = self.depth {
+            state.set_enabled(gl, glow::DEPTH_TEST, depth.test);
+            if depth.test {
+                state.depth_func(gl, depth.compare.to_gl());
+            }
+            state.depth_mask(gl, depth.write);
+            let bias = depth.bias_factor != 0.0 || depth.bias_units != 0.0;
+            state.set_enabled(gl, glow::POLYGON_OFFSET_FILL, bias);
+            if bias {
+                state.polygon_offset(gl, depth.bias_factor, depth.bias_units);
+            }
+        }
+        match self.cull_mode.to_gl() {
+            Some(face) => {
+                state.set_enabled(gl, glow::CULL_FACE, true);
+                state.front_face(gl, glow::CCW);
+                state.cull_face(gl, face);
+            }
+            None => state.set_enabled(gl, glow::CULL_FACE, false),
+        }
+
+        // point size is always enabled on WebGL and not a valid capability there
+        if self.topology == Topology::Points && !cfg!(target_arch = "wasm32") {
+            state.set_enabled(gl, glow::PROGRAM_POINT_SIZE, true);
+        }
+    }
+
+    fn flush(&mut self) {
+        if self.count > 0 || self.index_count > 0 {
+            self.apply_state();
+            unsafe {
+                let mode = self.topology.to_gl();
+                if self.count > 0 {
+                    self.gl
+                        .draw_arrays(mode, self.first as i32, self.count as i32);
+                }
+                if self.index_count > 0 {
+                    self.gl.draw_elements(
+                        mode,
+                        self.index_count as i32,
+                        glow::UNSIGNED_INT,
+                        (self.index_first * std::mem::size_of::<u32>()) as i32,
+                    );
+                }
+            }
+            if self.count > 0 {
+                self.record_draw(false, self.count);
+            }
+            if self.index_count > 0 {
+                self.record_draw(true, self.index_count);
+            }
+        }
+        self.first = self.vertex_cursor();
+        self.count = 0;
+        self.index_first = self.renderer.index_stream.cursor();
+        self.index_count = 0;
+    }
+
+    pub fn finish(mut self) {
+        self.flush();
+    }
 }
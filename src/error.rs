@@ -0,0 +1,70 @@
+use std::fmt;
+
+/// The shader stage that failed to compile.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ShaderStage {
+    Vertex,
+    Fragment,
+}
+
+impl ShaderStage {
+    pub(crate) fn from_gl(shader_type: u32) -> Self {
+        match shader_type {
+            glow::FRAGMENT_SHADER => Self::Fragment,
+            _ => Self::Vertex,
+        }
+    }
+}
+
+impl fmt::Display for ShaderStage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Vertex => write!(f, "vertex"),
+            Self::Fragment => write!(f, "fragment"),
+        }
+    }
+}
+
+/// Errors that can occur while talking to the GL context.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum GloxError {
+    /// A shader failed to compile. Contains the stage and the driver info log.
+    ShaderCompile { stage: ShaderStage, log: String },
+    /// The program failed to link. Contains the driver info log.
+    ProgramLink { log: String },
+    /// A GL object could not be created.
+    ResourceCreation { resource: &'static str, message: String },
+    /// `Glox::init` has not been called, or it failed.
+    NotInitialized,
+    /// The PLY source could not be parsed into vertices.
+    InvalidPly,
+    /// The pixel data does not match the texture size and format.
+    InvalidTextureData { expected: usize, actual: usize },
+    /// A texture update does not fit inside the texture.
+    TextureRegionOutOfBounds,
+    /// An image could not be read or decoded.
+    ImageDecode(String),
+}
+
+impl fmt::Display for GloxError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::ShaderCompile { stage, log } => {
+                write!(f, "failed to compile {stage} shader: {log}")
+            }
+            Self::ProgramLink { log } => write!(f, "failed to link program: {log}"),
+            Self::ResourceCreation { resource, message } => {
+                write!(f, "failed to create {resource}: {message}")
+            }
+            Self::NotInitialized => write!(f, "glox is not initialized"),
+            Self::InvalidPly => write!(f, "invalid ply source"),
+            Self::InvalidTextureData { expected, actual } => {
+                write!(f, "expected {expected} bytes of texture data, got {actual}")
+            }
+            Self::TextureRegionOutOfBounds => write!(f, "texture region out of bounds"),
+            Self::ImageDecode(message) => write!(f, "failed to decode image: {message}"),
+        }
+    }
+}
+
+impl std::error::Error for GloxError {}
@@ -0,0 +1,85 @@
+use glam::Vec4;
+use glow::HasContext as _;
+
+use crate::{Glox, GloxConfig};
+
+/// A rectangle of the framebuffer in pixels, with the origin in the lower left corner.
+#[derive(Default, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Viewport {
+    pub x: i32,
+    pub y: i32,
+    pub width: i32,
+    pub height: i32,
+}
+
+/// How `Glox::begin_frame` prepares the framebuffer. The default keeps the viewport and clears
+/// nothing.
+#[derive(Default, Clone, Copy, Debug, PartialEq)]
+pub struct FrameDesc {
+    /// The viewport to draw to, `None` keeps the current one.
+    pub viewport: Option<Viewport>,
+    /// Color to clear to, `None` keeps the color buffer.
+    pub clear_color: Option<Vec4>,
+    /// Depth to clear to, usually 1.0. `None` keeps the depth buffer.
+    pub clear_depth: Option<f32>,
+}
+
+impl FrameDesc {
+    /// Clears color to `GloxConfig::clear_color` and depth to 1.0, keeping the viewport.
+    pub fn from_config(config: &GloxConfig) -> Self {
+        Self {
+            viewport: None,
+            clear_color: Some(config.clear_color),
+            clear_depth: Some(1.0),
+        }
+    }
+}
+
+impl Glox {
+    /// Starts a frame: saves the host GL state as `begin` does, sets the viewport and clears
+    /// according to `desc` and resets the statistics. Every draw must happen between
+    /// `begin_frame` and `end_frame`.
+    ///
+    /// Glox remembers the GL state it sets for the whole frame, so GL calls made by others
+    /// before `end_frame` must restore the program, vertex array, unit 0 texture and draw state
+    /// they change.
+    pub fn begin_frame(&mut self, gl: &glow::Context, desc: FrameDesc) {
+        debug_assert!(!self.in_frame, "glox: begin_frame called twice without end_frame");
+        self.begin(gl);
+        self.in_frame = true;
+        self.reset_stats();
+        unsafe {
+            if let Some(viewport) = desc.viewport {
+                gl.viewport(viewport.x, viewport.y, viewport.width, viewport.height);
+            }
+            let mut mask = 0;
+            if let Some(c) = desc.clear_color {
+                gl.clear_color(c.x, c.y, c.z, c.w);
+                mask |= glow::COLOR_BUFFER_BIT;
+            }
+            if let Some(depth) = desc.clear_depth {
+                // depth writes must be on for the depth buffer to be cleared
+                gl.depth_mask(true);
+                gl.clear_depth_f32(depth);
+                mask |= glow::DEPTH_BUFFER_BIT;
+            }
+            if mask != 0 {
+                gl.clear(mask);
+            }
+            // the state tracker binds textures to unit 0
+            gl.active_texture(glow::TEXTURE0);
+        }
+        self.state.reset();
+    }
+
+    /// Ends a frame: fences and rotates the streaming buffers so later frames do not overwrite
+    /// data the GPU may still be reading, and restores the host GL state saved by
+    /// `begin_frame`.
+    pub fn end_frame(&mut self, gl: &glow::Context) {
+        debug_assert!(self.in_frame, "glox: end_frame called without begin_frame");
+        self.in_frame = false;
+        self.fence_slot(gl);
+        self.swap();
+        self.end(gl);
+    }
+}
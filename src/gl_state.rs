@@ -0,0 +1,314 @@
+use glow::HasContext as _;
+
+/// Remembers the GL state set through it and skips calls that would not change anything.
+///
+/// The state is only known for what has been set since the last `reset`, so it must be reset
+/// whenever someone else may have touched the context, e.g. in `Glox::begin_frame`. Where glox
+/// itself binds objects without going through the tracker, the affected binding is forgotten.
+#[derive(Default)]
+pub(crate) struct GlState {
+    program: Option<glow::Program>,
+    vertex_array: Option<glow::VertexArray>,
+    texture: Option<Option<glow::Texture>>,
+    capabilities: Vec<(u32, bool)>,
+    blend: Option<(u32, u32, u32)>,
+    depth_func: Option<u32>,
+    depth_mask: Option<bool>,
+    polygon_offset: Option<(f32, f32)>,
+    cull_face: Option<u32>,
+    front_face: Option<u32>,
+    avoided: usize,
+    texture_binds: usize,
+    program_switches: usize,
+}
+
+impl GlState {
+    /// Forgets the tracked state, keeping the counters.
+    pub fn reset(&mut self) {
+        *self = Self {
+            avoided: self.avoided,
+            texture_binds: self.texture_binds,
+            program_switches: self.program_switches,
+            ..Default::default()
+        };
+    }
+
+    /// Forgets the bound vertex array, after vertex arrays were bound around the tracker.
+    pub fn forget_vertex_array(&mut self) {
+        self.vertex_array = None;
+    }
+
+    /// Forgets `texture` if it is tracked as bound, as its name may be reused once deleted.
+    pub fn forget_texture(&mut self, texture: glow::Texture) {
+        if self.texture == Some(Some(texture)) {
+            self.texture = None;
+        }
+    }
+
+    /// Forgets `program` if it is tracked as in use, as its name may be reused once deleted.
+    pub fn forget_program(&mut self, program: glow::Program) {
+        if self.program == Some(program) {
+            self.program = None;
+        }
+    }
+
+    pub fn reset_counters(&mut self) {
+        self.avoided = 0;
+        self.texture_binds = 0;
+        self.program_switches = 0;
+    }
+
+    /// Number of state changes skipped because the state was already set.
+    pub fn avoided(&self) -> usize {
+        self.avoided
+    }
+
+    pub fn texture_binds(&self) -> usize {
+        self.texture_binds
+    }
+
+    pub fn program_switches(&self) -> usize {
+        self.program_switches
+    }
+
+    /// Stores `value` in `slot`, returning true if it differs from what was there.
+    fn update<T: PartialEq>(&mut self, slot: fn(&mut Self) -> &mut Option<T>, value: T) -> bool {
+        let slot = slot(self);
+        if slot.as_ref() == Some(&value) {
+            self.avoided += 1;
+            return false;
+        }
+        *slot = Some(value);
+        true
+    }
+
+    pub fn use_program(&mut self, gl: &glow::Context, program: glow::Program) {
+        if self.update(|s| &mut s.program, program) {
+            self.program_switches += 1;
+            unsafe { gl.use_program(Some(program)) }
+        }
+    }
+
+    pub fn bind_vertex_array(&mut self, gl: &glow::Context, vertex_array: glow::VertexArray) {
+        if self.update(|s| &mut s.vertex_array, vertex_array) {
+            unsafe { gl.bind_vertex_array(Some(vertex_array)) }
+        }
+    }
+
+    /// Binds `texture` to `TEXTURE_2D` of texture unit 0.
+    pub fn bind_texture(&mut self, gl: &glow::Context, texture: Option<glow::Texture>) {
+        if self.update(|s| &mut s.texture, texture) {
+            self.texture_binds += 1;
+            unsafe { gl.bind_texture(glow::TEXTURE_2D, texture) }
+        }
+    }
+
+    /// Binds `texture` to `TEXTURE_2D` of another texture unit, which is not tracked. Unit 0 must
+    /// go through `bind_texture`.
+    pub fn bind_texture_unit(
+        &mut self,
+        gl: &glow::Context,
+        unit: u32,
+        texture: Option<glow::Texture>,
+    ) {
+        debug_assert!(unit != 0, "glox: unit 0 is bound through the tracker");
+        self.texture_binds += 1;
+        unsafe {
+            gl.active_texture(glow::TEXTURE0 + unit);
+            gl.bind_texture(glow::TEXTURE_2D, texture);
+            gl.active_texture(glow::TEXTURE0);
+        }
+    }
+
+    pub fn set_enabled(&mut self, gl: &glow::Context, capability: u32, enabled: bool) {
+        match self.capabilities.iter_mut().find(|(cap, _)| *cap == capability) {
+            Some((_, current)) if *current == enabled => {
+                self.avoided += 1;
+                return;
+            }
+            Some((_, current)) => *current = enabled,
+            None => self.capabilities.push((capability, enabled)),
+        }
+        unsafe {
+            if enabled {
+                gl.enable(capability);
+            } else {
+                gl.disable(capability);
+            }
+        }
+    }
+
+    pub fn blend(&mut self, gl: &glow::Context, src: u32, dst: u32, equation: u32) {
+        if self.update(|s| &mut s.blend, (src, dst, equation)) {
+            unsafe {
+                gl.blend_func(src, dst);
+                gl.blend_equation(equation);
+            }
+        }
+    }
+
+    pub fn depth_func(&mut self, gl: &glow::Context, func: u32) {
+        if self.update(|s| &mut s.depth_func, func) {
+            unsafe { gl.depth_func(func) }
+        }
+    }
+
+    pub fn depth_mask(&mut self, gl: &glow::Context, write: bool) {
+        if self.update(|s| &mut s.depth_mask, write) {
+            unsafe { gl.depth_mask(write) }
+        }
+    }
+
+    pub fn polygon_offset(&mut self, gl: &glow::Context, factor: f32, units: f32) {
+        if self.update(|s| &mut s.polygon_offset, (factor, units)) {
+            unsafe { gl.polygon_offset(factor, units) }
+        }
+    }
+
+    pub fn cull_face(&mut self, gl: &glow::Context, face: u32) {
+        if self.update(|s| &mut s.cull_face, face) {
+            unsafe { gl.cull_face(face) }
+        }
+    }
+
+    pub fn front_face(&mut self, gl: &glow::Context, winding: u32) {
+        if self.update(|s| &mut s.front_face, winding) {
+            unsafe { gl.front_face(winding) }
+        }
+    }
+}
+
+/// A snapshot of the host's GL state that glox changes while drawing, see `Glox::begin`.
+pub(crate) struct SavedState {
+    program: Option<glow::Program>,
+    vertex_array: Option<glow::VertexArray>,
+    array_buffer: Option<glow::Buffer>,
+    active_texture: u32,
+    texture: Option<glow::Texture>,
+    /// Bindings of the other texture units glox used, see `save_texture_unit`.
+    texture_units: Vec<(u32, Option<glow::Texture>)>,
+    capabilities: Vec<(u32, bool)>,
+    blend_func: [u32; 4],
+    blend_equation: [u32; 2],
+    depth_func: u32,
+    depth_mask: bool,
+    polygon_offset: [f32; 2],
+    cull_face: u32,
+    front_face: u32,
+    clear_color: [f32; 4],
+    clear_depth: f32,
+    viewport: [i32; 4],
+}
+
+impl SavedState {
+    fn capabilities() -> &'static [u32] {
+        // point size is always enabled on WebGL and not a valid capability there
+        if cfg!(target_arch = "wasm32") {
+            &[glow::BLEND, glow::DEPTH_TEST, glow::CULL_FACE, glow::POLYGON_OFFSET_FILL]
+        } else {
+            &[
+                glow::BLEND,
+                glow::DEPTH_TEST,
+                glow::CULL_FACE,
+                glow::POLYGON_OFFSET_FILL,
+                glow::PROGRAM_POINT_SIZE,
+            ]
+        }
+    }
+
+    pub fn capture(gl: &glow::Context) -> Self {
+        unsafe {
+            let active_texture = gl.get_parameter_i32(glow::ACTIVE_TEXTURE) as u32;
+            gl.active_texture(glow::TEXTURE0);
+            let texture = gl.get_parameter_texture(glow::TEXTURE_BINDING_2D);
+            gl.active_texture(active_texture);
+
+            let polygon_offset = [glow::POLYGON_OFFSET_FACTOR, glow::POLYGON_OFFSET_UNITS]
+                .map(|parameter| gl.get_parameter_f32(parameter));
+            let mut clear_color = [0.0; 4];
+            gl.get_parameter_f32_slice(glow::COLOR_CLEAR_VALUE, &mut clear_color);
+            let mut viewport = [0; 4];
+            gl.get_parameter_i32_slice(glow::VIEWPORT, &mut viewport);
+
+            Self {
+                program: gl.get_parameter_program(glow::CURRENT_PROGRAM),
+                vertex_array: gl.get_parameter_vertex_array(glow::VERTEX_ARRAY_BINDING),
+                array_buffer: gl.get_parameter_buffer(glow::ARRAY_BUFFER_BINDING),
+                active_texture,
+                texture,
+                texture_units: Vec::new(),
+                capabilities: Self::capabilities()
+                    .iter()
+                    .map(|capability| (*capability, gl.is_enabled(*capability)))
+                    .collect(),
+                blend_func: [
+                    glow::BLEND_SRC_RGB,
+                    glow::BLEND_DST_RGB,
+                    glow::BLEND_SRC_ALPHA,
+                    glow::BLEND_DST_ALPHA,
+                ]
+                .map(|parameter| gl.get_parameter_i32(parameter) as u32),
+                blend_equation: [glow::BLEND_EQUATION_RGB, glow::BLEND_EQUATION_ALPHA]
+                    .map(|parameter| gl.get_parameter_i32(parameter) as u32),
+                depth_func: gl.get_parameter_i32(glow::DEPTH_FUNC) as u32,
+                depth_mask: gl.get_parameter_bool(glow::DEPTH_WRITEMASK),
+                polygon_offset,
+                cull_face: gl.get_parameter_i32(glow::CULL_FACE_MODE) as u32,
+                front_face: gl.get_parameter_i32(glow::FRONT_FACE) as u32,
+                clear_color,
+                clear_depth: gl.get_parameter_f32(glow::DEPTH_CLEAR_VALUE),
+                viewport,
+            }
+        }
+    }
+
+    /// Adds the binding of texture `unit` to the snapshot before glox first binds to it. Leaves
+    /// unit 0 active.
+    pub fn save_texture_unit(&mut self, gl: &glow::Context, unit: u32) {
+        if unit == 0 || self.texture_units.iter().any(|(saved, _)| *saved == unit) {
+            return;
+        }
+        unsafe {
+            gl.active_texture(glow::TEXTURE0 + unit);
+            let texture = gl.get_parameter_texture(glow::TEXTURE_BINDING_2D);
+            gl.active_texture(glow::TEXTURE0);
+            self.texture_units.push((unit, texture));
+        }
+    }
+
+    pub fn restore(&self, gl: &glow::Context) {
+        unsafe {
+            gl.use_program(self.program);
+            gl.bind_vertex_array(self.vertex_array);
+            gl.bind_buffer(glow::ARRAY_BUFFER, self.array_buffer);
+            for (unit, texture) in &self.texture_units {
+                gl.active_texture(glow::TEXTURE0 + unit);
+                gl.bind_texture(glow::TEXTURE_2D, *texture);
+            }
+            gl.active_texture(glow::TEXTURE0);
+            gl.bind_texture(glow::TEXTURE_2D, self.texture);
+            gl.active_texture(self.active_texture);
+            for (capability, enabled) in &self.capabilities {
+                if *enabled {
+                    gl.enable(*capability);
+                } else {
+                    gl.disable(*capability);
+                }
+            }
+            let [src_rgb, dst_rgb, src_alpha, dst_alpha] = self.blend_func;
+            gl.blend_func_separate(src_rgb, dst_rgb, src_alpha, dst_alpha);
+            let [equation_rgb, equation_alpha] = self.blend_equation;
+            gl.blend_equation_separate(equation_rgb, equation_alpha);
+            gl.depth_func(self.depth_func);
+            gl.depth_mask(self.depth_mask);
+            gl.polygon_offset(self.polygon_offset[0], self.polygon_offset[1]);
+            gl.cull_face(self.cull_face);
+            gl.front_face(self.front_face);
+            let [r, g, b, a] = self.clear_color;
+            gl.clear_color(r, g, b, a);
+            gl.clear_depth_f32(self.clear_depth);
+            let [x, y, width, height] = self.viewport;
+            gl.viewport(x, y, width, height);
+        }
+    }
+}
@@ -0,0 +1,90 @@
+use glam::{Vec2, Vec3, Vec4};
+
+use crate::{AttributeType, Pod, UvRect, VertexAttribute, VertexLayout};
+
+/// Per-instance data for `DrawBuilder::draw_instances` and `DrawBuilder::draw_billboards`.
+#[repr(C)]
+#[derive(Clone, Copy, Debug)]
+pub struct Instance {
+    pub position: [f32; 3],
+    /// Scale applied to the base mesh. Billboards use `x` as width and `z` as height.
+    pub scale: [f32; 3],
+    /// Multiplied with the vertex color.
+    pub color: [f32; 4],
+    /// Offset (`xy`) and size (`zw`) the base mesh uvs are mapped into.
+    pub uv_rect: [f32; 4],
+}
+
+impl Default for Instance {
+    fn default() -> Self {
+        Self {
+            position: [0.0; 3],
+            scale: [1.0; 3],
+            color: [1.0; 4],
+            uv_rect: [0.0, 0.0, 1.0, 1.0],
+        }
+    }
+}
+
+impl Instance {
+    pub fn new(position: Vec3, scale: Vec3, color: Vec4, uv_rect: Vec4) -> Self {
+        Self {
+            position: position.to_array(),
+            scale: scale.to_array(),
+            color: color.to_array(),
+            uv_rect: uv_rect.to_array(),
+        }
+    }
+
+    /// An instance of a billboard standing at `bottom_center` with the given width and height.
+    pub fn billboard(bottom_center: Vec3, size: Vec2, color: Vec4) -> Self {
+        Self {
+            position: bottom_center.to_array(),
+            scale: [size.x, size.x, size.y],
+            color: color.to_array(),
+            ..Default::default()
+        }
+    }
+
+    /// Maps the base mesh uvs into `uv`, e.g. a region of an `Atlas`.
+    pub fn with_uv_rect(mut self, uv: UvRect) -> Self {
+        self.uv_rect = uv.to_offset_size().to_array();
+        self
+    }
+}
+
+// SAFETY: `Instance` is `repr(C)` and made of floats only
+unsafe impl Pod for Instance {}
+
+impl VertexLayout for Instance {
+    const ATTRIBUTES: &'static [VertexAttribute] = &[
+        VertexAttribute {
+            location: 3,
+            components: 3,
+            ty: AttributeType::F32,
+            normalized: false,
+            offset: std::mem::offset_of!(Instance, position),
+        },
+        VertexAttribute {
+            location: 4,
+            components: 3,
+            ty: AttributeType::F32,
+            normalized: false,
+            offset: std::mem::offset_of!(Instance, scale),
+        },
+        VertexAttribute {
+            location: 5,
+            components: 4,
+            ty: AttributeType::F32,
+            normalized: false,
+            offset: std::mem::offset_of!(Instance, color),
+        },
+        VertexAttribute {
+            location: 6,
+            components: 4,
+            ty: AttributeType::F32,
+            normalized: false,
+            offset: std::mem::offset_of!(Instance, uv_rect),
+        },
+    ];
+}
@@ -0,0 +1,99 @@
+use glow::HasContext as _;
+
+use crate::Pod;
+
+/// Component type of a vertex attribute.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum AttributeType {
+    F32,
+    /// Half precision float, stored as the bits in a `u16`.
+    F16,
+    I8,
+    U8,
+    I16,
+    U16,
+    I32,
+    U32,
+}
+
+impl AttributeType {
+    pub fn to_gl(self) -> u32 {
+        match self {
+            Self::F32 => glow::FLOAT,
+            Self::F16 => glow::HALF_FLOAT,
+            Self::I8 => glow::BYTE,
+            Self::U8 => glow::UNSIGNED_BYTE,
+            Self::I16 => glow::SHORT,
+            Self::U16 => glow::UNSIGNED_SHORT,
+            Self::I32 => glow::INT,
+            Self::U32 => glow::UNSIGNED_INT,
+        }
+    }
+
+    /// Size of one component in bytes.
+    pub fn size(self) -> usize {
+        match self {
+            Self::I8 | Self::U8 => 1,
+            Self::F16 | Self::I16 | Self::U16 => 2,
+            Self::F32 | Self::I32 | Self::U32 => 4,
+        }
+    }
+}
+
+/// One attribute of a vertex type, read by the vertex shader as a float vector.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct VertexAttribute {
+    /// The `layout(location = ..)` of the shader input.
+    pub location: u32,
+    /// Number of components, 1 to 4.
+    pub components: i32,
+    pub ty: AttributeType,
+    /// Whether integer components are mapped to 0..1 (unsigned) or -1..1 (signed) instead of
+    /// being converted to float as is.
+    pub normalized: bool,
+    /// Byte offset of the attribute in the vertex.
+    pub offset: usize,
+}
+
+/// Describes how a vertex type is laid out in memory so it can be streamed with
+/// `Glox::draw_builder_with_layout` or uploaded with `Mesh::new`. Every attribute must lie
+/// within the type.
+///
+/// The built-in programs read position, color and uv from locations 0, 1 and 2, the lit program
+/// also the normal from location 7, and instanced draws use locations 3 to 6 for `Instance`.
+/// Layouts used with custom programs are free to use any locations.
+pub trait VertexLayout: Pod {
+    const ATTRIBUTES: &'static [VertexAttribute];
+}
+
+/// Points the attributes of `V` at `offset` bytes into the buffer bound to `ARRAY_BUFFER`,
+/// advancing once per vertex if `divisor` is 0 or once per `divisor` instances otherwise.
+pub(crate) fn attrib_pointers<V: VertexLayout>(gl: &glow::Context, offset: usize, divisor: u32) {
+    let stride = std::mem::size_of::<V>() as i32;
+    for attribute in V::ATTRIBUTES {
+        unsafe {
+            gl.enable_vertex_attrib_array(attribute.location);
+            gl.vertex_attrib_pointer_f32(
+                attribute.location,
+                attribute.components,
+                attribute.ty.to_gl(),
+                attribute.normalized,
+                stride,
+                (offset + attribute.offset) as i32,
+            );
+            if divisor != 0 {
+                gl.vertex_attrib_divisor(attribute.location, divisor);
+            }
+        }
+    }
+}
+
+/// Undoes `attrib_pointers` so the attributes do not leak into other draws.
+pub(crate) fn disable_attribs<V: VertexLayout>(gl: &glow::Context) {
+    for attribute in V::ATTRIBUTES {
+        unsafe {
+            gl.vertex_attrib_divisor(attribute.location, 0);
+            gl.disable_vertex_attrib_array(attribute.location);
+        }
+    }
+}
@@ -1,5 +1,6 @@
 mod camera;
 mod shader;
+mod gl_state;
 mod ray;
 mod vertices;
 pub use vertices::*;
@@ -9,72 +10,372 @@ mod draw_builder;
 pub use draw_builder::*;
 mod vertex;
 pub use vertex::*;
+mod layout;
+pub use layout::*;
+mod pod;
+pub use pod::*;
+mod packed_vertex;
+pub use packed_vertex::*;
+mod lit_vertex;
+pub use lit_vertex::*;
+mod error;
+pub use error::*;
+mod stream;
+pub use stream::*;
+mod config;
+pub use config::*;
+mod topology;
+pub use topology::*;
+mod mesh;
+pub use mesh::*;
+mod instance;
+pub use instance::*;
+mod state;
+pub use state::*;
+mod render_queue;
+pub use render_queue::*;
+mod texture;
+pub use texture::*;
+mod atlas;
+pub use atlas::*;
+mod program;
+pub use program::*;
+mod lights;
+pub use lights::*;
+mod frame;
+pub use frame::*;
+mod stats;
+pub use stats::*;
+mod sync;
+#[cfg(feature = "image")]
+mod decode;
+#[cfg(feature = "image")]
+pub use decode::*;
 
 use glow::{HasContext, Program};
+use std::any::TypeId;
+use std::collections::HashMap;
 use std::mem::size_of;
 
 #[derive(Default)]
 pub struct Glox {
+    config: GloxConfig,
     program: Option<Program>,
-    pub vertex_array: Option<glow::VertexArray>,
-    pub vertex_buffers: Vec<glow::Buffer>,
-    pub vertex_buffer_current: usize,
-    pub vertex_buffer_len: usize,
-    pub vertex_buffer_vertex_index: usize,
+    instanced_program: Option<Program>,
+    lit_program: Option<Program>,
+    white_texture: Option<glow::Texture>,
+    /// Per vertex layout, one vertex array per streaming buffer in the ring with the attributes
+    /// and index buffer already set up.
+    vertex_arrays: HashMap<TypeId, Vec<glow::VertexArray>>,
+    pub(crate) vertex_stream: StreamBuffer,
+    pub(crate) index_stream: StreamBuffer,
+    pub(crate) instance_stream: StreamBuffer,
+    /// Draw calls, vertices and indices of the current frame, the rest of `GloxStats` is
+    /// gathered in `stats`.
+    pub(crate) frame_stats: GloxStats,
+    pub(crate) state: gl_state::GlState,
+    saved_state: Option<gl_state::SavedState>,
+    pub(crate) in_frame: bool,
+    /// GPU progress on each slot of the streaming buffer rings.
+    pub(crate) slots: Vec<sync::SlotSync>,
+    pub(crate) uniforms: UniformCache,
 }
 
 impl Glox {
-    pub fn init(&mut self, gl: &glow::Context) {
-        self.vertex_buffer_len = 1024 * 1024; // 1 million vertices
+    /// Creates an uninitialized `Glox` that `init` will set up according to `config`.
+    pub fn with_config(config: GloxConfig) -> Self {
+        let mut glox = Self::default();
+        glox.config = config;
+        glox
+    }
+
+    pub fn config(&self) -> &GloxConfig {
+        &self.config
+    }
+
+    /// Creates the GL objects. Calling it again, e.g. to re-create them for a new context,
+    /// first destroys those of the previous call.
+    pub fn init(&mut self, gl: &glow::Context) -> Result<(), GloxError> {
+        if self.has_live_objects() {
+            self.destroy(gl);
+        }
+        let result = self.create_objects(gl);
+        if result.is_err() {
+            self.destroy(gl);
+        }
+        result
+    }
+
+    fn create_objects(&mut self, gl: &glow::Context) -> Result<(), GloxError> {
+        // the element array binding is part of the vertex array state, so keep a vertex array of
+        // ours bound while the index buffers are created, not the one of the host
+        let vertex_array = Self::create_vertex_array(gl)?;
+        unsafe {
+            gl.bind_vertex_array(Some(vertex_array));
+        }
+        let streams = self.create_streams(gl);
+        unsafe {
+            gl.bind_vertex_array(None);
+            gl.delete_vertex_array(vertex_array);
+        }
+        streams?;
+        self.slots.resize_with(self.vertex_stream.buffers().len(), Default::default);
+        self.stream_vertex_arrays::<Vertex>(gl)?;
+        unsafe {
+            gl.bind_vertex_array(None);
+        }
+
+        self.white_texture = Some(Self::create_white_texture(gl)?);
+        self.program = Some(shader::compile_program(
+            gl,
+            self.shader_version(),
+            &shader::shader_sources(),
+        )?);
+        self.instanced_program = Some(shader::compile_program(
+            gl,
+            self.shader_version(),
+            &shader::instanced_shader_sources(),
+        )?);
+        self.lit_program = Some(shader::compile_program(
+            gl,
+            self.shader_version(),
+            &shader::lit_shader_sources(),
+        )?);
+        Ok(())
+    }
+
+    fn create_streams(&mut self, gl: &glow::Context) -> Result<(), GloxError> {
+        // vertex layouts vary in size, so the vertex stream is addressed in bytes
+        self.vertex_stream = StreamBuffer::new(
+            gl,
+            glow::ARRAY_BUFFER,
+            1,
+            self.config.vertex_capacity * size_of::<Vertex>(),
+            self.config.buffer_count,
+        )?;
+        self.index_stream = StreamBuffer::new(
+            gl,
+            glow::ELEMENT_ARRAY_BUFFER,
+            size_of::<u32>(),
+            self.config.index_capacity,
+            self.config.buffer_count,
+        )?;
+        self.instance_stream = StreamBuffer::new(
+            gl,
+            glow::ARRAY_BUFFER,
+            size_of::<Instance>(),
+            self.config.instance_capacity,
+            self.config.buffer_count,
+        )?;
+        Ok(())
+    }
+
+    /// The vertex arrays streaming vertices of layout `V`, one per slot of the ring, created on
+    /// first use. Leaves one of them bound.
+    pub(crate) fn stream_vertex_arrays<V: VertexLayout>(
+        &mut self,
+        gl: &glow::Context,
+    ) -> Result<&[glow::VertexArray], GloxError> {
+        if self.vertex_stream.buffers().is_empty() {
+            return Err(GloxError::NotInitialized);
+        }
+        let key = TypeId::of::<V>();
+        if !self.vertex_arrays.contains_key(&key) {
+            let mut vertex_arrays = Vec::new();
+            let buffers = self.vertex_stream.buffers().iter().zip(self.index_stream.buffers());
+            for (vertex_buffer, index_buffer) in buffers {
+                let vertex_array = match Self::create_vertex_array(gl) {
+                    Ok(vertex_array) => vertex_array,
+                    Err(err) => {
+                        for vertex_array in vertex_arrays {
+                            unsafe { gl.delete_vertex_array(vertex_array) };
+                        }
+                        return Err(err);
+                    }
+                };
+                unsafe {
+                    gl.bind_vertex_array(Some(vertex_array));
+                    gl.bind_buffer(glow::ARRAY_BUFFER, Some(*vertex_buffer));
+                    attrib_pointers::<V>(gl, 0, 0);
+                    gl.bind_buffer(glow::ELEMENT_ARRAY_BUFFER, Some(*index_buffer));
+                }
+                vertex_arrays.push(vertex_array);
+            }
+            self.state.forget_vertex_array();
+            self.vertex_arrays.insert(key, vertex_arrays);
+        }
+        Ok(&self.vertex_arrays[&key])
+    }
+
+    fn create_vertex_array(gl: &glow::Context) -> Result<glow::VertexArray, GloxError> {
+        unsafe { gl.create_vertex_array() }.map_err(|message| GloxError::ResourceCreation {
+            resource: "vertex array",
+            message,
+        })
+    }
+
+    /// Creates the 1x1 white texture bound for untextured draws.
+    fn create_white_texture(gl: &glow::Context) -> Result<glow::Texture, GloxError> {
         unsafe {
-            self.vertex_array = Some(
-                gl.create_vertex_array()
-                    .expect("failed to create vertex array"),
+            let texture = gl
+                .create_texture()
+                .map_err(|message| GloxError::ResourceCreation {
+                    resource: "texture",
+                    message,
+                })?;
+            gl.bind_texture(glow::TEXTURE_2D, Some(texture));
+            let data = [255u8, 255u8, 255u8, 255u8]; // white texture
+            gl.tex_image_2d(
+                glow::TEXTURE_2D,
+                0,
+                glow::RGBA as i32,
+                1,
+                1,
+                0,
+                glow::RGBA,
+                glow::UNSIGNED_BYTE,
+                glow::PixelUnpackData::Slice(Some(&data)),
             );
-            for _ in 0..3 {
-                let vertex_buffer = gl.create_buffer().expect("failed to create buffer");
-                gl.bind_buffer(glow::ARRAY_BUFFER, Some(vertex_buffer));
-                gl.buffer_data_size(
-                    glow::ARRAY_BUFFER,
-                    self.vertex_buffer_len as i32 * size_of::<Vertex>() as i32,
-                    glow::DYNAMIC_DRAW,
-                );
-                self.vertex_buffers.push(vertex_buffer);
+            gl.generate_mipmap(glow::TEXTURE_2D);
+            Ok(texture)
+        }
+    }
+
+    pub(crate) fn shader_version(&self) -> &str {
+        self.config
+            .shader_version
+            .as_deref()
+            .unwrap_or(shader::shader_version())
+    }
+
+    /// Saves the GL state glox is about to change, to be restored by `end`. Does nothing if
+    /// `GloxConfig::preserve_gl_state` is off or the state is already saved.
+    ///
+    /// Saved are the program, vertex array and array buffer bindings, the texture bindings of
+    /// unit 0 and of the units used with `DrawBuilder::set_sampler`, the active texture unit,
+    /// blending, depth, culling, polygon offset, the clear values and the viewport.
+    pub fn begin(&mut self, gl: &glow::Context) {
+        if self.config.preserve_gl_state && self.saved_state.is_none() {
+            self.saved_state = Some(gl_state::SavedState::capture(gl));
+        }
+    }
+
+    /// Adds texture `unit` to the state saved by `begin`, if any.
+    pub(crate) fn save_texture_unit(&mut self, gl: &glow::Context, unit: u32) {
+        if let Some(saved_state) = &mut self.saved_state {
+            saved_state.save_texture_unit(gl, unit);
+        }
+    }
+
+    /// Restores the GL state saved by `begin`.
+    pub fn end(&mut self, gl: &glow::Context) {
+        if let Some(saved_state) = self.saved_state.take() {
+            saved_state.restore(gl);
+        }
+    }
+
+    /// Deletes every GL object owned by this instance and resets it to its `Default` state,
+    /// keeping the config.
+    pub fn destroy(&mut self, gl: &glow::Context) {
+        unsafe {
+            if let Some(program) = self.program.take() {
+                gl.delete_program(program);
+            }
+            if let Some(program) = self.instanced_program.take() {
+                gl.delete_program(program);
+            }
+            if let Some(program) = self.lit_program.take() {
+                gl.delete_program(program);
+            }
+            if let Some(texture) = self.white_texture.take() {
+                gl.delete_texture(texture);
             }
+            for vertex_array in self.vertex_arrays.drain().flat_map(|(_, arrays)| arrays) {
+                gl.delete_vertex_array(vertex_array);
+            }
+        }
+        self.delete_fences(gl);
+        self.vertex_stream.destroy(gl);
+        self.index_stream.destroy(gl);
+        self.instance_stream.destroy(gl);
+        *self = Self::with_config(std::mem::take(&mut self.config));
+    }
 
-            let program = gl.create_program().expect("Cannot create program");
-            let _: Vec<_> = shader::shader_sources()
-                .iter()
-                .map(|(shader_type, shader_source)| {
-                    let shader = gl
-                        .create_shader(*shader_type)
-                        .expect("Cannot create shader");
-                    gl.shader_source(
-                        shader,
-                        &format!("{}\n{}", shader::shader_version(), shader_source),
-                    );
-                    gl.compile_shader(shader);
-                    assert!(
-                        gl.get_shader_compile_status(shader),
-                        "Failed to compile {shader_type}: {}",
-                        gl.get_shader_info_log(shader)
-                    );
-                    gl.attach_shader(program, shader);
-                    shader
-                })
-                .collect();
-            gl.link_program(program);
-
-            self.program = Some(program);
+    fn has_live_objects(&self) -> bool {
+        self.program.is_some()
+            || self.instanced_program.is_some()
+            || self.lit_program.is_some()
+            || self.white_texture.is_some()
+            || !self.vertex_arrays.is_empty()
+            || self.vertex_stream.current().is_some()
+            || self.index_stream.current().is_some()
+            || self.instance_stream.current().is_some()
+    }
+
+    pub(crate) fn swap(&mut self) {
+        self.vertex_stream.swap();
+        self.index_stream.swap();
+        self.instance_stream.swap();
+    }
+
+    pub(crate) fn reset_stats(&mut self) {
+        self.frame_stats = GloxStats::default();
+        self.state.reset_counters();
+        self.vertex_stream.reset_stats();
+        self.index_stream.reset_stats();
+        self.instance_stream.reset_stats();
+    }
+
+    /// Work done since `begin_frame`. Read it after `end_frame` for the numbers of a whole
+    /// frame.
+    pub fn stats(&self) -> GloxStats {
+        let streams = [&self.vertex_stream, &self.index_stream, &self.instance_stream];
+        GloxStats {
+            bytes_uploaded: streams.iter().map(|stream| stream.bytes_written()).sum(),
+            texture_binds: self.state.texture_binds(),
+            program_switches: self.state.program_switches(),
+            state_changes_avoided: self.state.avoided(),
+            buffer_wraps: streams.iter().map(|stream| stream.growths()).sum(),
+            peak_vertex_bytes: self.vertex_stream.peak(),
+            peak_indices: self.index_stream.peak(),
+            peak_instances: self.instance_stream.peak(),
+            ..self.frame_stats
+        }
+    }
+
+    /// Flushes and growths of the streaming buffers since `begin_frame`, useful for sizing them.
+    pub fn stream_stats(&self) -> StreamStats {
+        let stats = self.stats();
+        StreamStats {
+            flushes: stats.flushes,
+            growths: stats.buffer_wraps,
         }
     }
 
-    pub fn swap(&mut self) {
-        self.vertex_buffer_vertex_index = 0;
-        self.vertex_buffer_current = (self.vertex_buffer_current + 1) % self.vertex_buffers.len();
+    /// Number of GL state changes the draw builders skipped since `begin_frame` because the
+    /// state was already set.
+    pub fn avoided_state_changes(&self) -> usize {
+        self.state.avoided()
     }
 
-    pub fn draw_builder<'a>(&'a mut self, gl: &'a glow::Context, camera:&'a dyn Camera) -> DrawBuilder<'a> {
+    pub fn draw_builder<'a>(&'a mut self, gl: &'a glow::Context, camera:&'a dyn Camera) -> Result<DrawBuilder<'a>, GloxError> {
         DrawBuilder::new(self, gl, camera)
     }
+
+    /// Like `draw_builder`, but streams vertices of layout `V` instead of `Vertex`.
+    pub fn draw_builder_with_layout<'a, V: VertexLayout>(
+        &'a mut self,
+        gl: &'a glow::Context,
+        camera: &'a dyn Camera,
+    ) -> Result<DrawBuilder<'a, V>, GloxError> {
+        DrawBuilder::new(self, gl, camera)
+    }
+}
+
+impl Drop for Glox {
+    fn drop(&mut self) {
+        if cfg!(debug_assertions) && self.has_live_objects() {
+            eprintln!("glox: Glox dropped without calling destroy, its GL objects are leaked");
+        }
+    }
 }
@@ -0,0 +1,54 @@
+use glam::Vec3;
+
+/// Most point lights the lit program takes into account, the rest are ignored.
+pub const MAX_POINT_LIGHTS: usize = 8;
+
+/// A light infinitely far away, like the sun.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct DirectionalLight {
+    /// Direction the light travels in.
+    pub direction: Vec3,
+    pub color: Vec3,
+}
+
+/// A light shining in every direction from `position`, fading with distance `d` by
+/// `1 / (1 + linear * d + quadratic * d * d)`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PointLight {
+    pub position: Vec3,
+    pub color: Vec3,
+    pub linear: f32,
+    pub quadratic: f32,
+}
+
+impl PointLight {
+    /// A light fading to about a tenth of its color at `range`.
+    pub fn new(position: Vec3, color: Vec3, range: f32) -> Self {
+        Self {
+            position,
+            color,
+            linear: 1.0 / range,
+            quadratic: 8.0 / (range * range),
+        }
+    }
+}
+
+/// The lights of a scene, set with `DrawBuilder::lights`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Lights {
+    /// Light reaching every surface regardless of its normal.
+    pub ambient: Vec3,
+    pub directional: Option<DirectionalLight>,
+    /// At most `MAX_POINT_LIGHTS` are used.
+    pub point_lights: Vec<PointLight>,
+}
+
+impl Default for Lights {
+    fn default() -> Self {
+        Self {
+            ambient: Vec3::splat(0.2),
+            directional: None,
+            point_lights: Vec::new(),
+        }
+    }
+}
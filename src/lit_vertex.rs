@@ -0,0 +1,95 @@
+use glam::{Vec2, Vec3, Vec4};
+
+use crate::{AttributeType, PackedVertex, Pod, Vertex, VertexAttribute, VertexLayout};
+
+/// A `Vertex` with a normal, drawn lit while `DrawBuilder::lights` is set. Stream it with
+/// `Glox::draw_builder_with_layout` or upload it with `Mesh::new`. Layouts without a normal,
+/// like `Vertex`, are drawn unlit by the lit program.
+#[repr(C)]
+#[derive(Default, Clone, Copy, Debug, PartialEq)]
+pub struct LitVertex {
+    pub position: [f32; 3],
+    pub color: [f32; 4],
+    pub uv: [f32; 2],
+    /// Direction the surface faces. Zero for vertices that are not lit, like billboards and
+    /// lines.
+    pub normal: [f32; 3],
+}
+
+impl LitVertex {
+    pub fn new(position: Vec3, color: Vec4, uv: Vec2, normal: Vec3) -> Self {
+        Self {
+            position: position.to_array(),
+            color: color.to_array(),
+            uv: uv.to_array(),
+            normal: normal.to_array(),
+        }
+    }
+
+    /// `vertex` with `normal` added.
+    pub fn from_vertex(vertex: Vertex, normal: Vec3) -> Self {
+        Self::new(vertex.position.into(), vertex.color.into(), vertex.uv.into(), normal)
+    }
+
+    pub fn with_normal(mut self, normal: Vec3) -> Self {
+        self.normal = normal.to_array();
+        self
+    }
+}
+
+/// An unlit vertex.
+impl From<Vertex> for LitVertex {
+    fn from(vertex: Vertex) -> Self {
+        Self::from_vertex(vertex, Vec3::ZERO)
+    }
+}
+
+/// Drops the normal.
+impl From<LitVertex> for Vertex {
+    fn from(vertex: LitVertex) -> Self {
+        Self::new(vertex.position.into(), vertex.color.into(), vertex.uv.into())
+    }
+}
+
+/// Drops the normal.
+impl From<LitVertex> for PackedVertex {
+    fn from(vertex: LitVertex) -> Self {
+        Self::new(vertex.position.into(), vertex.color.into(), vertex.uv.into())
+    }
+}
+
+// SAFETY: `LitVertex` is `repr(C)` and made of floats only
+unsafe impl Pod for LitVertex {}
+
+impl VertexLayout for LitVertex {
+    const ATTRIBUTES: &'static [VertexAttribute] = &[
+        VertexAttribute {
+            location: 0,
+            components: 3,
+            ty: AttributeType::F32,
+            normalized: false,
+            offset: std::mem::offset_of!(LitVertex, position),
+        },
+        VertexAttribute {
+            location: 1,
+            components: 4,
+            ty: AttributeType::F32,
+            normalized: false,
+            offset: std::mem::offset_of!(LitVertex, color),
+        },
+        VertexAttribute {
+            location: 2,
+            components: 2,
+            ty: AttributeType::F32,
+            normalized: false,
+            offset: std::mem::offset_of!(LitVertex, uv),
+        },
+        VertexAttribute {
+            location: 7,
+            components: 3,
+            ty: AttributeType::F32,
+            normalized: false,
+            offset: std::mem::offset_of!(LitVertex, normal),
+        },
+    ];
+}
@@ -0,0 +1,141 @@
+use glow::HasContext as _;
+
+use glam::{Vec2, Vec3, Vec4};
+
+use crate::{
+    GloxError, UvRect, Vertex, VertexLayout, attrib_pointers, billboard_vertices_indexed,
+    cast_slice,
+};
+
+/// Geometry uploaded once into its own `STATIC_DRAW` buffers, drawn with `DrawBuilder::draw_mesh`.
+pub struct Mesh {
+    vertex_array: Option<glow::VertexArray>,
+    vertex_buffer: Option<glow::Buffer>,
+    index_buffer: Option<glow::Buffer>,
+    count: usize,
+}
+
+impl Mesh {
+    /// Uploads `vertices`, and `indices` if given, into new GL buffers. Any `VertexLayout` can
+    /// be used, as long as the program drawing the mesh reads its attributes.
+    ///
+    /// Must not be called while a `DrawBuilder` is alive, as it binds vertex arrays behind the
+    /// back of the builder. The previously bound vertex array is bound again afterwards.
+    pub fn new<V: VertexLayout>(
+        gl: &glow::Context,
+        vertices: &[V],
+        indices: Option<&[u32]>,
+    ) -> Result<Self, GloxError> {
+        let mut mesh = Self {
+            vertex_array: None,
+            vertex_buffer: None,
+            index_buffer: None,
+            count: indices.map_or(vertices.len(), |indices| indices.len()),
+        };
+        let previous = unsafe { gl.get_parameter_vertex_array(glow::VERTEX_ARRAY_BINDING) };
+        let result = mesh.upload(gl, vertices, indices);
+        unsafe {
+            gl.bind_vertex_array(previous);
+        }
+        match result {
+            Ok(()) => Ok(mesh),
+            Err(err) => {
+                mesh.destroy(gl);
+                Err(err)
+            }
+        }
+    }
+
+    /// A unit billboard quad, 1 wide and 1 high with its bottom center at the origin, to be used
+    /// as the base mesh of `DrawBuilder::draw_billboards`.
+    pub fn billboard(gl: &glow::Context) -> Result<Self, GloxError> {
+        let (vertices, indices) = billboard_vertices_indexed::<Vertex>(
+            Vec3::ZERO,
+            Vec4::ONE,
+            Vec3::NEG_Y,
+            Vec2::ONE,
+            UvRect::FULL,
+        );
+        Self::new(gl, &vertices, Some(&indices))
+    }
+
+    fn upload<V: VertexLayout>(
+        &mut self,
+        gl: &glow::Context,
+        vertices: &[V],
+        indices: Option<&[u32]>,
+    ) -> Result<(), GloxError> {
+        let create_buffer = || unsafe {
+            gl.create_buffer()
+                .map_err(|message| GloxError::ResourceCreation {
+                    resource: "mesh buffer",
+                    message,
+                })
+        };
+        unsafe {
+            let vertex_array = gl.create_vertex_array().map_err(|message| {
+                GloxError::ResourceCreation {
+                    resource: "vertex array",
+                    message,
+                }
+            })?;
+            self.vertex_array = Some(vertex_array);
+            gl.bind_vertex_array(Some(vertex_array));
+
+            let vertex_buffer = create_buffer()?;
+            self.vertex_buffer = Some(vertex_buffer);
+            gl.bind_buffer(glow::ARRAY_BUFFER, Some(vertex_buffer));
+            gl.buffer_data_u8_slice(glow::ARRAY_BUFFER, cast_slice(vertices), glow::STATIC_DRAW);
+            attrib_pointers::<V>(gl, 0, 0);
+
+            if let Some(indices) = indices {
+                let index_buffer = create_buffer()?;
+                self.index_buffer = Some(index_buffer);
+                gl.bind_buffer(glow::ELEMENT_ARRAY_BUFFER, Some(index_buffer));
+                gl.buffer_data_u8_slice(
+                    glow::ELEMENT_ARRAY_BUFFER,
+                    cast_slice(indices),
+                    glow::STATIC_DRAW,
+                );
+            }
+        }
+        Ok(())
+    }
+
+    /// Number of vertices, or indices if the mesh is indexed, drawn by `DrawBuilder::draw_mesh`.
+    pub fn count(&self) -> usize {
+        self.count
+    }
+
+    pub fn is_indexed(&self) -> bool {
+        self.index_buffer.is_some()
+    }
+
+    pub(crate) fn vertex_array(&self) -> Option<glow::VertexArray> {
+        self.vertex_array
+    }
+
+    /// Deletes the GL objects of the mesh.
+    pub fn destroy(&mut self, gl: &glow::Context) {
+        unsafe {
+            if let Some(vertex_array) = self.vertex_array.take() {
+                gl.delete_vertex_array(vertex_array);
+            }
+            if let Some(buffer) = self.vertex_buffer.take() {
+                gl.delete_buffer(buffer);
+            }
+            if let Some(buffer) = self.index_buffer.take() {
+                gl.delete_buffer(buffer);
+            }
+        }
+        self.count = 0;
+    }
+}
+
+impl Drop for Mesh {
+    fn drop(&mut self) {
+        if cfg!(debug_assertions) && self.vertex_array.is_some() {
+            eprintln!("glox: Mesh dropped without calling destroy, its GL objects are leaked");
+        }
+    }
+}
@@ -0,0 +1,234 @@
+use glam::{Vec2, Vec3, Vec4};
+
+use crate::{AttributeType, Pod, Vertex, VertexAttribute, VertexLayout};
+
+const COLOR: VertexAttribute = VertexAttribute {
+    location: 1,
+    components: 4,
+    ty: AttributeType::U8,
+    normalized: true,
+    offset: 0,
+};
+
+const UV: VertexAttribute = VertexAttribute {
+    location: 2,
+    components: 2,
+    ty: AttributeType::F16,
+    normalized: false,
+    offset: 0,
+};
+
+/// A 20 byte alternative to the 36 byte `Vertex`, with an 8 bit color and half float uvs.
+/// Drawn by the built-in program like `Vertex`, and every generator in this crate can produce
+/// it. Has no normal, so it is not lit.
+#[repr(C)]
+#[derive(Default, Clone, Copy, Debug, PartialEq)]
+pub struct PackedVertex {
+    pub position: [f32; 3],
+    /// Color with each channel mapped from 0..255 to 0..1.
+    pub color: [u8; 4],
+    /// Uv as the bits of half floats, see `f16_bits`.
+    pub uv: [u16; 2],
+}
+
+impl PackedVertex {
+    pub fn new(position: Vec3, color: Vec4, uv: Vec2) -> Self {
+        Self {
+            position: position.to_array(),
+            color: unorm8(color),
+            uv: [f16_bits(uv.x), f16_bits(uv.y)],
+        }
+    }
+}
+
+impl From<Vertex> for PackedVertex {
+    fn from(vertex: Vertex) -> Self {
+        Self::new(vertex.position.into(), vertex.color.into(), vertex.uv.into())
+    }
+}
+
+// SAFETY: `PackedVertex` is `repr(C)` with fields of 12, 4 and 4 bytes, so without padding
+unsafe impl Pod for PackedVertex {}
+
+impl VertexLayout for PackedVertex {
+    const ATTRIBUTES: &'static [VertexAttribute] = &[
+        VertexAttribute {
+            location: 0,
+            components: 3,
+            ty: AttributeType::F32,
+            normalized: false,
+            offset: std::mem::offset_of!(PackedVertex, position),
+        },
+        VertexAttribute {
+            offset: std::mem::offset_of!(PackedVertex, color),
+            ..COLOR
+        },
+        VertexAttribute {
+            offset: std::mem::offset_of!(PackedVertex, uv),
+            ..UV
+        },
+    ];
+}
+
+/// A 16 byte vertex whose position is stored as multiples of a scale in 16 bit integers, for
+/// geometry within `i16::MAX * scale` of the origin. Draw it with the scale as model matrix,
+/// e.g. `DrawBuilder::set_model(Mat4::from_scale(Vec3::splat(scale)))`. Like `PackedVertex`
+/// it has no normal and is not lit.
+#[repr(C)]
+#[derive(Default, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct QuantizedVertex {
+    /// Position divided by the scale.
+    pub position: [i16; 3],
+    padding: u16,
+    pub color: [u8; 4],
+    pub uv: [u16; 2],
+}
+
+impl QuantizedVertex {
+    pub fn new(position: Vec3, color: Vec4, uv: Vec2, scale: f32) -> Self {
+        let position = (position / scale).round();
+        let position = position.clamp(Vec3::splat(i16::MIN as f32), Vec3::splat(i16::MAX as f32));
+        Self {
+            position: position.to_array().map(|c| c as i16),
+            padding: 0,
+            color: unorm8(color),
+            uv: [f16_bits(uv.x), f16_bits(uv.y)],
+        }
+    }
+
+    /// Quantizes a vertex, e.g. one made by the generators in this crate.
+    pub fn from_vertex(vertex: Vertex, scale: f32) -> Self {
+        Self::new(vertex.position.into(), vertex.color.into(), vertex.uv.into(), scale)
+    }
+}
+
+// SAFETY: `QuantizedVertex` is `repr(C)` with fields of 6, 2, 4 and 4 bytes, so without padding
+unsafe impl Pod for QuantizedVertex {}
+
+impl VertexLayout for QuantizedVertex {
+    const ATTRIBUTES: &'static [VertexAttribute] = &[
+        VertexAttribute {
+            location: 0,
+            components: 3,
+            ty: AttributeType::I16,
+            normalized: false,
+            offset: std::mem::offset_of!(QuantizedVertex, position),
+        },
+        VertexAttribute {
+            offset: std::mem::offset_of!(QuantizedVertex, color),
+            ..COLOR
+        },
+        VertexAttribute {
+            offset: std::mem::offset_of!(QuantizedVertex, uv),
+            ..UV
+        },
+    ];
+}
+
+fn unorm8(color: Vec4) -> [u8; 4] {
+    (color.clamp(Vec4::ZERO, Vec4::ONE) * 255.0).round().to_array().map(|c| c as u8)
+}
+
+/// Converts `value` to the bits of the nearest half float, saturating to infinity.
+pub fn f16_bits(value: f32) -> u16 {
+    let bits = value.to_bits();
+    let sign = ((bits >> 16) & 0x8000) as u16;
+    let exponent = ((bits >> 23) & 0xff) as i32;
+    let mantissa = bits & 0x007f_ffff;
+    if exponent == 0xff {
+        // infinity stays infinity, nan stays nan
+        return sign | 0x7c00 | if mantissa != 0 { 0x0200 } else { 0 };
+    }
+    let exponent = exponent - 127 + 15;
+    if exponent >= 0x1f {
+        return sign | 0x7c00;
+    }
+    if exponent <= 0 {
+        // too small for a normal half float, shift the mantissa into a subnormal
+        if exponent < -10 {
+            return sign;
+        }
+        return sign | round_half_even(mantissa | 0x0080_0000, (14 - exponent) as u32) as u16;
+    }
+    // a carry from rounding correctly moves into the exponent
+    sign | round_half_even(((exponent as u32) << 23) | mantissa, 13) as u16
+}
+
+/// Shifts `value` right by `shift` bits, rounding ties to even like IEEE 754.
+fn round_half_even(value: u32, shift: u32) -> u32 {
+    let half = 1 << (shift - 1);
+    let rest = value & ((1 << shift) - 1);
+    let shifted = value >> shift;
+    if rest > half || (rest == half && shifted & 1 == 1) {
+        shifted + 1
+    } else {
+        shifted
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn f16_bits_known_values() {
+        assert_eq!(f16_bits(0.0), 0x0000);
+        assert_eq!(f16_bits(-0.0), 0x8000);
+        assert_eq!(f16_bits(1.0), 0x3c00);
+        assert_eq!(f16_bits(-2.0), 0xc000);
+        assert_eq!(f16_bits(0.5), 0x3800);
+        // largest finite half
+        assert_eq!(f16_bits(65504.0), 0x7bff);
+    }
+
+    #[test]
+    fn f16_bits_overflow_to_infinity() {
+        assert_eq!(f16_bits(65536.0), 0x7c00);
+        assert_eq!(f16_bits(-1.0e10), 0xfc00);
+        assert_eq!(f16_bits(f32::INFINITY), 0x7c00);
+        assert_eq!(f16_bits(f32::NEG_INFINITY), 0xfc00);
+        // rounds up past the largest finite half
+        assert_eq!(f16_bits(65520.0), 0x7c00);
+    }
+
+    #[test]
+    fn f16_bits_subnormals() {
+        // smallest subnormal, 2^-24
+        assert_eq!(f16_bits(2.0f32.powi(-24)), 0x0001);
+        assert_eq!(f16_bits(-(2.0f32.powi(-24))), 0x8001);
+        // largest subnormal, 1023 * 2^-24
+        assert_eq!(f16_bits(1023.0 * 2.0f32.powi(-24)), 0x03ff);
+        // smallest normal, 2^-14
+        assert_eq!(f16_bits(2.0f32.powi(-14)), 0x0400);
+        // too small for a subnormal
+        assert_eq!(f16_bits(2.0f32.powi(-30)), 0x0000);
+    }
+
+    #[test]
+    fn f16_bits_rounding_carries_into_exponent() {
+        // just below 2.0 with more precision than a half has, rounds up to 2.0
+        assert_eq!(f16_bits(2.0 - 2.0f32.powi(-12)), 0x4000);
+        // rounds to the nearest half, 1 + 2^-10 is the next half after 1.0
+        assert_eq!(f16_bits(1.0 + 2.0f32.powi(-10)), 0x3c01);
+    }
+
+    #[test]
+    fn f16_bits_ties_round_to_even() {
+        // halfway between 0x3c00 and 0x3c01, the even one wins
+        assert_eq!(f16_bits(1.0 + 2.0f32.powi(-11)), 0x3c00);
+        // halfway between 0x3c01 and 0x3c02
+        assert_eq!(f16_bits(1.0 + 3.0 * 2.0f32.powi(-11)), 0x3c02);
+        // just above the tie rounds up
+        assert_eq!(f16_bits(1.0 + 2.0f32.powi(-11) + 2.0f32.powi(-20)), 0x3c01);
+        // subnormal ties, 2^-25 is halfway between 0 and the smallest subnormal
+        assert_eq!(f16_bits(2.0f32.powi(-25)), 0x0000);
+        assert_eq!(f16_bits(3.0 * 2.0f32.powi(-25)), 0x0002);
+    }
+
+    #[test]
+    fn f16_bits_nan() {
+        let bits = f16_bits(f32::NAN);
+        assert_eq!(bits & 0x7c00, 0x7c00);
+        assert_ne!(bits & 0x03ff, 0);
+    }
+}
@@ -0,0 +1,27 @@
+/// Plain data that can be viewed as bytes, like `bytemuck::Pod`.
+///
+/// # Safety
+///
+/// The type must be `#[repr(C)]`, `#[repr(C, packed)]` or a primitive, contain no padding,
+/// pointers or references, and every bit pattern must be a valid value.
+pub unsafe trait Pod: Copy + 'static {}
+
+// SAFETY: primitives without padding or invalid bit patterns
+unsafe impl Pod for u8 {}
+unsafe impl Pod for i8 {}
+unsafe impl Pod for u16 {}
+unsafe impl Pod for i16 {}
+unsafe impl Pod for u32 {}
+unsafe impl Pod for i32 {}
+unsafe impl Pod for f32 {}
+// SAFETY: arrays of `Pod` have no padding between their elements
+unsafe impl<T: Pod, const N: usize> Pod for [T; N] {}
+
+/// Views `items` as their bytes without copying.
+pub fn cast_slice<T: Pod>(items: &[T]) -> &[u8] {
+    // SAFETY: `Pod` types have no padding, so every byte is initialized, and u8 has no
+    // alignment requirement
+    unsafe {
+        std::slice::from_raw_parts(items.as_ptr() as *const u8, std::mem::size_of_val(items))
+    }
+}
@@ -0,0 +1,136 @@
+use std::collections::HashMap;
+
+use glam::{Mat3, Mat4, Vec2, Vec3, Vec4};
+use glow::HasContext as _;
+
+use crate::{Glox, GloxError, shader};
+
+/// A shader program created by `Glox::create_program`. Must be freed with
+/// `Glox::delete_program`.
+#[derive(Debug, PartialEq, Eq, Hash)]
+pub struct ProgramHandle {
+    program: glow::Program,
+}
+
+impl ProgramHandle {
+    /// The underlying GL program.
+    pub fn raw(&self) -> glow::Program {
+        self.program
+    }
+}
+
+/// A value that can be assigned to a uniform with `DrawBuilder::set_uniform`.
+pub trait UniformValue {
+    /// Uploads the value to `location` of the program in use.
+    fn upload(&self, gl: &glow::Context, location: Option<&glow::UniformLocation>);
+}
+
+impl UniformValue for f32 {
+    fn upload(&self, gl: &glow::Context, location: Option<&glow::UniformLocation>) {
+        unsafe { gl.uniform_1_f32(location, *self) }
+    }
+}
+
+impl UniformValue for i32 {
+    fn upload(&self, gl: &glow::Context, location: Option<&glow::UniformLocation>) {
+        unsafe { gl.uniform_1_i32(location, *self) }
+    }
+}
+
+impl UniformValue for Vec2 {
+    fn upload(&self, gl: &glow::Context, location: Option<&glow::UniformLocation>) {
+        unsafe { gl.uniform_2_f32(location, self.x, self.y) }
+    }
+}
+
+impl UniformValue for Vec3 {
+    fn upload(&self, gl: &glow::Context, location: Option<&glow::UniformLocation>) {
+        unsafe { gl.uniform_3_f32(location, self.x, self.y, self.z) }
+    }
+}
+
+impl UniformValue for Vec4 {
+    fn upload(&self, gl: &glow::Context, location: Option<&glow::UniformLocation>) {
+        unsafe { gl.uniform_4_f32(location, self.x, self.y, self.z, self.w) }
+    }
+}
+
+impl UniformValue for Mat3 {
+    fn upload(&self, gl: &glow::Context, location: Option<&glow::UniformLocation>) {
+        unsafe { gl.uniform_matrix_3_f32_slice(location, false, self.as_ref()) }
+    }
+}
+
+impl UniformValue for Mat4 {
+    fn upload(&self, gl: &glow::Context, location: Option<&glow::UniformLocation>) {
+        unsafe { gl.uniform_matrix_4_f32_slice(location, false, self.as_ref()) }
+    }
+}
+
+/// Uniform locations looked up so far, per program.
+#[derive(Default)]
+pub(crate) struct UniformCache {
+    programs: HashMap<glow::Program, HashMap<String, Option<glow::UniformLocation>>>,
+}
+
+impl UniformCache {
+    /// The location of `name` in `program`, asking the driver only the first time.
+    // locations are only `Copy` on native targets
+    #[allow(clippy::clone_on_copy)]
+    pub fn location(
+        &mut self,
+        gl: &glow::Context,
+        program: glow::Program,
+        name: &str,
+    ) -> Option<glow::UniformLocation> {
+        let locations = self.programs.entry(program).or_default();
+        if let Some(location) = locations.get(name) {
+            return location.clone();
+        }
+        let location = unsafe { gl.get_uniform_location(program, name) };
+        locations.insert(name.to_owned(), location.clone());
+        location
+    }
+
+    /// Drops the cached locations of a deleted program.
+    pub fn forget(&mut self, program: glow::Program) {
+        self.programs.remove(&program);
+    }
+}
+
+impl Glox {
+    /// Compiles and links a program from GLSL sources without a `#version` line, which is
+    /// prepended from `GloxConfig::shader_version`.
+    ///
+    /// Vertices are supplied at attribute locations 0 (`vec3` position), 1 (`vec4` color),
+    /// 2 (`vec2` uv) and 7 (`vec3` normal). The uniforms `view_projection`, `model`,
+    /// `normal_matrix` (`mat3`) and `point_size` are set by `DrawBuilder` when the program
+    /// declares them, as are the uniforms of the built-in lit program while
+    /// `DrawBuilder::lights` is set. The texture bound with `DrawBuilder::bind_texture` is on
+    /// unit 0.
+    pub fn create_program(
+        &self,
+        gl: &glow::Context,
+        vertex_src: &str,
+        fragment_src: &str,
+    ) -> Result<ProgramHandle, GloxError> {
+        let program = shader::compile_program(
+            gl,
+            self.shader_version(),
+            &[
+                (glow::VERTEX_SHADER, vertex_src),
+                (glow::FRAGMENT_SHADER, fragment_src),
+            ],
+        )?;
+        Ok(ProgramHandle { program })
+    }
+
+    /// Deletes a program created with `create_program`.
+    pub fn delete_program(&mut self, gl: &glow::Context, program: ProgramHandle) {
+        self.uniforms.forget(program.program);
+        self.state.forget_program(program.program);
+        unsafe {
+            gl.delete_program(program.program);
+        }
+    }
+}
@@ -0,0 +1,145 @@
+use std::ops::Range;
+
+use glam::Vec3;
+
+use crate::{BlendMode, Camera, CullMode, DepthState, Glox, GloxError, Vertex};
+
+/// The state a `RenderQueue` submission is drawn with. Submissions with equal state are merged.
+#[derive(Default, Clone, Copy, Debug, PartialEq)]
+pub struct DrawState {
+    /// Texture to sample, `None` for the shared white texture.
+    pub texture: Option<glow::Texture>,
+    pub blend_mode: BlendMode,
+    pub depth: DepthState,
+    pub cull_mode: CullMode,
+}
+
+impl DrawState {
+    fn is_transparent(&self) -> bool {
+        self.blend_mode != BlendMode::Opaque
+    }
+}
+
+struct Submission {
+    state: DrawState,
+    vertices: Range<usize>,
+    indices: Range<usize>,
+    center: Vec3,
+}
+
+/// Collects triangles during a frame and draws them in `execute` with as few draw calls as
+/// possible. Opaque submissions are drawn first, grouped by state and front-to-back, then
+/// transparent submissions back-to-front relative to the camera eye.
+#[derive(Default)]
+pub struct RenderQueue {
+    submissions: Vec<Submission>,
+    vertices: Vec<Vertex>,
+    indices: Vec<u32>,
+}
+
+impl RenderQueue {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Queues triangles drawn with `state`.
+    pub fn push(&mut self, state: DrawState, vertices: &[Vertex]) -> &mut Self {
+        // everything is drawn indexed so merged submissions keep their order
+        let indices: Vec<u32> = (0..vertices.len() as u32).collect();
+        self.push_indexed(state, vertices, &indices)
+    }
+
+    /// Queues indexed triangles drawn with `state`. `indices` are relative to `vertices`.
+    pub fn push_indexed(
+        &mut self,
+        state: DrawState,
+        vertices: &[Vertex],
+        indices: &[u32],
+    ) -> &mut Self {
+        if vertices.is_empty() {
+            return self;
+        }
+        let center = vertices
+            .iter()
+            .map(|vertex| Vec3::from(vertex.position))
+            .sum::<Vec3>()
+            / vertices.len() as f32;
+        let vertex_start = self.vertices.len();
+        let index_start = self.indices.len();
+        self.vertices.extend_from_slice(vertices);
+        self.indices.extend_from_slice(indices);
+        self.submissions.push(Submission {
+            state,
+            vertices: vertex_start..self.vertices.len(),
+            indices: index_start..self.indices.len(),
+            center,
+        });
+        self
+    }
+
+    pub fn len(&self) -> usize {
+        self.submissions.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.submissions.is_empty()
+    }
+
+    /// Discards everything queued.
+    pub fn clear(&mut self) {
+        self.submissions.clear();
+        self.vertices.clear();
+        self.indices.clear();
+    }
+
+    /// Draws everything queued and clears the queue, also if drawing fails.
+    pub fn execute(
+        &mut self,
+        glox: &mut Glox,
+        gl: &glow::Context,
+        camera: &dyn Camera,
+    ) -> Result<(), GloxError> {
+        let eye = camera.eye();
+        let distance = |submission: &Submission| submission.center.distance_squared(eye);
+
+        let (mut opaque, mut transparent): (Vec<&Submission>, Vec<&Submission>) = self
+            .submissions
+            .iter()
+            .partition(|submission| !submission.state.is_transparent());
+
+        // group opaque submissions by state, ordering the groups by their nearest submission
+        opaque.sort_by(|a, b| distance(a).total_cmp(&distance(b)));
+        let mut groups: Vec<Vec<&Submission>> = Vec::new();
+        for submission in opaque {
+            match groups.iter_mut().find(|group| group[0].state == submission.state) {
+                Some(group) => group.push(submission),
+                None => groups.push(vec![submission]),
+            }
+        }
+
+        transparent.sort_by(|a, b| distance(b).total_cmp(&distance(a)));
+
+        let mut draw = match glox.draw_builder(gl, camera) {
+            Ok(draw) => draw,
+            Err(err) => {
+                self.clear();
+                return Err(err);
+            }
+        };
+        for submission in groups.into_iter().flatten().chain(transparent) {
+            let state = submission.state;
+            draw.bind_texture(state.texture)
+                .blend_mode(state.blend_mode)
+                .depth_state(state.depth)
+                .cull_mode(state.cull_mode);
+            draw.push_indexed(
+                &self.vertices[submission.vertices.clone()],
+                &self.indices[submission.indices.clone()],
+            );
+        }
+        draw.finish();
+
+        self.clear();
+        Ok(())
+    }
+}
@@ -1,3 +1,7 @@
+use glow::HasContext as _;
+
+use crate::{GloxError, MAX_POINT_LIGHTS, ShaderStage};
+
 pub fn shader_version() -> &'static str {
     if cfg!(target_arch = "wasm32") {
         "#version 300 es"
@@ -6,6 +10,21 @@ pub fn shader_version() -> &'static str {
     }
 }
 
+const FRAGMENT_SHADER_SOURCE: &str = r#"
+                precision mediump float;
+                uniform sampler2D tex;
+                in vec4 vertexColor;
+                in vec2 uv;
+                out vec4 fragColor;
+                void main() {
+                    vec4 color = texture(tex, uv) * vertexColor;
+                    if (color.a == 0.0) {
+                        discard;
+                    }
+                    fragColor = color;
+                }
+            "#;
+
 pub fn shader_sources() -> [(u32, &'static str);2] {
     let (vertex_shader_source, fragment_shader_source) = (
         r#"
@@ -15,31 +34,202 @@ pub fn shader_sources() -> [(u32, &'static str);2] {
                 out vec4 vertexColor;
                 out vec2 uv;
                 uniform mat4 view_projection;
+                uniform mat4 model;
+                uniform float point_size;
                 void main() {
-                    gl_Position = view_projection * vec4(aPos, 1.0);
+                    gl_Position = view_projection * model * vec4(aPos, 1.0);
+                    gl_PointSize = point_size;
                     uv = aUv;
                     vertexColor = aColor;
                 }
             "#,
-        r#"
-                precision mediump float;
+        FRAGMENT_SHADER_SOURCE,
+    );
+
+    
+    [
+        (glow::VERTEX_SHADER, vertex_shader_source),
+        (glow::FRAGMENT_SHADER, fragment_shader_source),
+    ]
+}
+
+/// Sources of the program used for instanced draws. Shares the fragment shader with
+/// `shader_sources`.
+pub fn instanced_shader_sources() -> [(u32, &'static str); 2] {
+    let vertex_shader_source = r#"
+                layout(location = 0) in vec3 aPos;
+                layout(location = 1) in vec4 aColor;
+                layout(location = 2) in vec2 aUv;
+                layout(location = 3) in vec3 iPosition;
+                layout(location = 4) in vec3 iScale;
+                layout(location = 5) in vec4 iColor;
+                layout(location = 6) in vec4 iUvRect;
+                out vec4 vertexColor;
+                out vec2 uv;
+                uniform mat4 view_projection;
+                uniform mat4 view;
+                uniform mat4 model;
+                uniform bool billboard;
+                void main() {
+                    vec3 local = aPos * iScale;
+                    vec3 world = iPosition + local;
+                    if (billboard) {
+                        // rotate around the z axis to face the camera, like billboard_vertices
+                        vec3 right = -normalize(vec3(view[0][0], view[1][0], 0.0));
+                        world = iPosition + right * local.x + vec3(0.0, 0.0, local.z);
+                    }
+                    gl_Position = view_projection * model * vec4(world, 1.0);
+                    uv = iUvRect.xy + aUv * iUvRect.zw;
+                    vertexColor = aColor * iColor;
+                }
+            "#;
+
+    [
+        (glow::VERTEX_SHADER, vertex_shader_source),
+        (glow::FRAGMENT_SHADER, FRAGMENT_SHADER_SOURCE),
+    ]
+}
+
+/// Sources of the program used when `DrawBuilder::lights` is set, with the size of the
+/// `point_lights` array defined from `MAX_POINT_LIGHTS`.
+pub fn lit_shader_sources() -> [(u32, String); 2] {
+    let vertex_shader_source = r#"
+                layout(location = 0) in vec3 aPos;
+                layout(location = 1) in vec4 aColor;
+                layout(location = 2) in vec2 aUv;
+                layout(location = 7) in vec3 aNormal;
+                out vec4 vertexColor;
+                out vec2 uv;
+                out vec3 worldPos;
+                out vec3 normal;
+                uniform mat4 view_projection;
+                uniform mat4 model;
+                uniform mat3 normal_matrix;
+                uniform float point_size;
+                void main() {
+                    vec4 world = model * vec4(aPos, 1.0);
+                    gl_Position = view_projection * world;
+                    gl_PointSize = point_size;
+                    uv = aUv;
+                    vertexColor = aColor;
+                    worldPos = world.xyz;
+                    normal = normal_matrix * aNormal;
+                }
+            "#;
+    let fragment_shader_source = r#"
+                precision highp float;
+                struct PointLight {
+                    vec3 position;
+                    vec3 color;
+                    float linear;
+                    float quadratic;
+                };
                 uniform sampler2D tex;
+                uniform vec3 ambient;
+                uniform vec3 light_direction;
+                uniform vec3 light_color;
+                uniform PointLight point_lights[MAX_POINT_LIGHTS];
+                uniform int point_light_count;
                 in vec4 vertexColor;
                 in vec2 uv;
+                in vec3 worldPos;
+                in vec3 normal;
                 out vec4 fragColor;
                 void main() {
                     vec4 color = texture(tex, uv) * vertexColor;
                     if (color.a == 0.0) {
                         discard;
                     }
-                    fragColor = color;
+                    // vertices without a normal are not lit
+                    vec3 light = vec3(1.0);
+                    if (dot(normal, normal) > 0.0) {
+                        // light both sides of a surface
+                        vec3 n = normalize(gl_FrontFacing ? normal : -normal);
+                        light = ambient + light_color * max(dot(n, -light_direction), 0.0);
+                        for (int i = 0; i < point_light_count; i++) {
+                            vec3 to_light = point_lights[i].position - worldPos;
+                            float d = length(to_light);
+                            float attenuation = 1.0 / (1.0 + point_lights[i].linear * d
+                                + point_lights[i].quadratic * d * d);
+                            float diffuse = max(dot(n, to_light / max(d, 0.0001)), 0.0);
+                            light += point_lights[i].color * diffuse * attenuation;
+                        }
+                    }
+                    fragColor = vec4(color.rgb * light, color.a);
                 }
-            "#,
-    );
+            "#;
 
-    
     [
-        (glow::VERTEX_SHADER, vertex_shader_source),
-        (glow::FRAGMENT_SHADER, fragment_shader_source),
+        (glow::VERTEX_SHADER, vertex_shader_source.to_owned()),
+        (
+            glow::FRAGMENT_SHADER,
+            format!("#define MAX_POINT_LIGHTS {MAX_POINT_LIGHTS}\n{fragment_shader_source}"),
+        ),
     ]
 }
+
+/// Compiles and links a program from the given sources, prefixing each with `version`.
+/// Every GL object created along the way is deleted again if compilation or linking fails.
+pub fn compile_program<S: AsRef<str>>(
+    gl: &glow::Context,
+    version: &str,
+    sources: &[(u32, S)],
+) -> Result<glow::Program, GloxError> {
+    unsafe {
+        let program = gl
+            .create_program()
+            .map_err(|message| GloxError::ResourceCreation {
+                resource: "program",
+                message,
+            })?;
+
+        let mut shaders = Vec::with_capacity(sources.len());
+        let mut result = Ok(());
+        for (shader_type, shader_source) in sources {
+            let shader = match gl.create_shader(*shader_type) {
+                Ok(shader) => shader,
+                Err(message) => {
+                    result = Err(GloxError::ResourceCreation {
+                        resource: "shader",
+                        message,
+                    });
+                    break;
+                }
+            };
+            gl.shader_source(shader, &format!("{}\n{}", version, shader_source.as_ref()));
+            gl.compile_shader(shader);
+            if !gl.get_shader_compile_status(shader) {
+                result = Err(GloxError::ShaderCompile {
+                    stage: ShaderStage::from_gl(*shader_type),
+                    log: gl.get_shader_info_log(shader),
+                });
+                gl.delete_shader(shader);
+                break;
+            }
+            gl.attach_shader(program, shader);
+            shaders.push(shader);
+        }
+
+        if result.is_ok() {
+            gl.link_program(program);
+            if !gl.get_program_link_status(program) {
+                result = Err(GloxError::ProgramLink {
+                    log: gl.get_program_info_log(program),
+                });
+            }
+        }
+
+        for shader in shaders {
+            gl.detach_shader(program, shader);
+            gl.delete_shader(shader);
+        }
+
+        match result {
+            Ok(()) => Ok(program),
+            Err(err) => {
+                gl.delete_program(program);
+                Err(err)
+            }
+        }
+    }
+}
@@ -0,0 +1,127 @@
+/// How the output of a draw is combined with what is already in the framebuffer.
+#[derive(Default, Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum BlendMode {
+    /// Blending is disabled.
+    Opaque,
+    /// `src * src_alpha + dst * (1 - src_alpha)`, for straight alpha colors.
+    Alpha,
+    /// `src + dst * (1 - src_alpha)`, for colors already multiplied by their alpha.
+    #[default]
+    PremultipliedAlpha,
+    /// `src * src_alpha + dst`.
+    Additive,
+    /// `src * dst`.
+    Multiply,
+    /// Raw GL blend factors and equation, e.g. `glow::ONE` and `glow::FUNC_ADD`.
+    Custom { src: u32, dst: u32, equation: u32 },
+}
+
+impl BlendMode {
+    /// Returns the source factor, destination factor and equation, or `None` for `Opaque`.
+    pub fn to_gl(self) -> Option<(u32, u32, u32)> {
+        match self {
+            Self::Opaque => None,
+            Self::Alpha => Some((glow::SRC_ALPHA, glow::ONE_MINUS_SRC_ALPHA, glow::FUNC_ADD)),
+            Self::PremultipliedAlpha => {
+                Some((glow::ONE, glow::ONE_MINUS_SRC_ALPHA, glow::FUNC_ADD))
+            }
+            Self::Additive => Some((glow::SRC_ALPHA, glow::ONE, glow::FUNC_ADD)),
+            Self::Multiply => Some((glow::DST_COLOR, glow::ZERO, glow::FUNC_ADD)),
+            Self::Custom { src, dst, equation } => Some((src, dst, equation)),
+        }
+    }
+}
+
+/// Comparison used by the depth test.
+#[derive(Default, Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum DepthCompare {
+    Never,
+    #[default]
+    Less,
+    Equal,
+    LessEqual,
+    Greater,
+    NotEqual,
+    GreaterEqual,
+    Always,
+}
+
+impl DepthCompare {
+    pub fn to_gl(self) -> u32 {
+        match self {
+            Self::Never => glow::NEVER,
+            Self::Less => glow::LESS,
+            Self::Equal => glow::EQUAL,
+            Self::LessEqual => glow::LEQUAL,
+            Self::Greater => glow::GREATER,
+            Self::NotEqual => glow::NOTEQUAL,
+            Self::GreaterEqual => glow::GEQUAL,
+            Self::Always => glow::ALWAYS,
+        }
+    }
+}
+
+/// Depth test and depth write settings of a draw.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct DepthState {
+    pub test: bool,
+    /// Depth is only written when `test` is enabled as well.
+    pub write: bool,
+    pub compare: DepthCompare,
+    /// Slope scaled part of the depth bias, see `glPolygonOffset`.
+    pub bias_factor: f32,
+    /// Constant part of the depth bias, see `glPolygonOffset`.
+    pub bias_units: f32,
+}
+
+impl Default for DepthState {
+    fn default() -> Self {
+        Self {
+            test: true,
+            write: true,
+            compare: DepthCompare::default(),
+            bias_factor: 0.0,
+            bias_units: 0.0,
+        }
+    }
+}
+
+impl DepthState {
+    /// No depth test and no depth writes.
+    pub fn disabled() -> Self {
+        Self {
+            test: false,
+            write: false,
+            ..Default::default()
+        }
+    }
+
+    /// Tests against the depth buffer without writing to it, for translucent draws.
+    pub fn read_only() -> Self {
+        Self {
+            write: false,
+            ..Default::default()
+        }
+    }
+}
+
+/// Which faces are discarded. Front faces are wound counter-clockwise, which is what the
+/// generators in `vertices` produce when looking at their front side.
+#[derive(Default, Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum CullMode {
+    #[default]
+    None,
+    Back,
+    Front,
+}
+
+impl CullMode {
+    /// Returns the face to cull, or `None` if culling is disabled.
+    pub fn to_gl(self) -> Option<u32> {
+        match self {
+            Self::None => None,
+            Self::Back => Some(glow::BACK),
+            Self::Front => Some(glow::FRONT),
+        }
+    }
+}
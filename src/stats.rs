@@ -0,0 +1,36 @@
+use std::time::Duration;
+
+/// Work done by glox since the last `Glox::begin_frame`, read with `Glox::stats`.
+#[derive(Default, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct GloxStats {
+    /// Draw calls issued, instanced draws counting once.
+    pub draw_calls: usize,
+    /// Vertices drawn without indices, including every instance of instanced draws.
+    pub vertices: usize,
+    /// Indices drawn, including every instance of instanced draws.
+    pub indices: usize,
+    /// Bytes written to the streaming vertex, index and instance buffers.
+    pub bytes_uploaded: usize,
+    /// Texture bindings that reached the driver.
+    pub texture_binds: usize,
+    /// Program switches that reached the driver.
+    pub program_switches: usize,
+    /// State changes skipped because the state was already set.
+    pub state_changes_avoided: usize,
+    /// Times a builder had to draw early because a streaming buffer ran out of space.
+    pub flushes: usize,
+    /// Times a streaming buffer was grown and writing restarted at its beginning.
+    pub buffer_wraps: usize,
+    /// Time spent waiting for the GPU to finish reading a streaming buffer before reusing it.
+    /// Always zero on WebGL, which cannot block.
+    pub fence_wait: Duration,
+    /// Times the streaming buffers were reallocated because the GPU did not finish reading them
+    /// in time, or the context has no sync objects.
+    pub buffer_orphans: usize,
+    /// Highest number of bytes in use in the current streaming vertex buffer.
+    pub peak_vertex_bytes: usize,
+    /// Highest number of indices in use in the current streaming index buffer.
+    pub peak_indices: usize,
+    /// Highest number of instances in use in the current streaming instance buffer.
+    pub peak_instances: usize,
+}
@@ -0,0 +1,197 @@
+use glow::HasContext as _;
+
+use crate::GloxError;
+
+/// Counters describing how the streaming buffers coped with the submitted data.
+#[derive(Default, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct StreamStats {
+    /// Number of times a builder had to draw early because the buffer ran out of space.
+    pub flushes: usize,
+    /// Number of times a buffer was reallocated with a larger capacity.
+    pub growths: usize,
+}
+
+/// A ring of GPU buffers that are written sequentially during a frame and rotated on `swap`.
+///
+/// When a write does not fit into the remaining space, the current buffer is grown, which orphans
+/// its old storage so draws already issued this frame keep their data.
+#[derive(Default)]
+pub struct StreamBuffer {
+    target: u32,
+    stride: usize,
+    buffers: Vec<glow::Buffer>,
+    capacities: Vec<usize>,
+    current: usize,
+    cursor: usize,
+    len: usize,
+    growths: usize,
+    peak: usize,
+    bytes_written: usize,
+}
+
+impl StreamBuffer {
+    /// Creates `count` buffers bound to `target`, each holding `len` elements of `stride` bytes.
+    pub fn new(
+        gl: &glow::Context,
+        target: u32,
+        stride: usize,
+        len: usize,
+        count: usize,
+    ) -> Result<Self, GloxError> {
+        let mut stream = Self {
+            target,
+            stride,
+            len,
+            ..Default::default()
+        };
+        for _ in 0..count.max(1) {
+            let buffer = unsafe { gl.create_buffer() }.map_err(|message| {
+                stream.destroy(gl);
+                GloxError::ResourceCreation {
+                    resource: "stream buffer",
+                    message,
+                }
+            })?;
+            stream.buffers.push(buffer);
+            stream.capacities.push(0);
+            stream.allocate(gl, stream.buffers.len() - 1);
+        }
+        Ok(stream)
+    }
+
+    fn allocate(&mut self, gl: &glow::Context, index: usize) {
+        unsafe {
+            gl.bind_buffer(self.target, Some(self.buffers[index]));
+            gl.buffer_data_size(
+                self.target,
+                (self.len * self.stride) as i32,
+                glow::DYNAMIC_DRAW,
+            );
+        }
+        self.capacities[index] = self.len;
+    }
+
+    /// The buffer currently being written to, if any.
+    pub fn current(&self) -> Option<glow::Buffer> {
+        self.buffers.get(self.current).copied()
+    }
+
+    /// Every buffer in the ring.
+    pub fn buffers(&self) -> &[glow::Buffer] {
+        &self.buffers
+    }
+
+    /// Position of the current buffer in the ring.
+    pub fn current_index(&self) -> usize {
+        self.current
+    }
+
+    /// Element index where the next write will land.
+    pub fn cursor(&self) -> usize {
+        self.cursor
+    }
+
+    /// Capacity, in elements, that every buffer in the ring will have.
+    pub fn capacity(&self) -> usize {
+        self.len
+    }
+
+    /// Number of reallocations since the last `reset_stats`. Each one restarts writing at the
+    /// start of the buffer.
+    pub fn growths(&self) -> usize {
+        self.growths
+    }
+
+    /// Highest number of elements in use in a buffer since the last `reset_stats`.
+    pub fn peak(&self) -> usize {
+        self.peak
+    }
+
+    /// Bytes written since the last `reset_stats`.
+    pub fn bytes_written(&self) -> usize {
+        self.bytes_written
+    }
+
+    pub fn reset_stats(&mut self) {
+        self.growths = 0;
+        self.peak = 0;
+        self.bytes_written = 0;
+    }
+
+    /// Returns true if `count` more elements fit into the current buffer.
+    pub fn fits(&self, count: usize) -> bool {
+        self.cursor + count <= self.capacities.get(self.current).copied().unwrap_or(0)
+    }
+
+    /// Grows the current buffer so at least `count` elements fit, starting a fresh range at 0.
+    pub fn grow(&mut self, gl: &glow::Context, count: usize) {
+        if self.buffers.is_empty() {
+            return;
+        }
+        self.len = (self.len * 2).max(count);
+        self.cursor = 0;
+        self.growths += 1;
+        self.allocate(gl, self.current);
+    }
+
+    /// Moves the cursor forward to the next multiple of `align` elements.
+    pub fn align(&mut self, align: usize) {
+        self.cursor = self.cursor.next_multiple_of(align.max(1));
+    }
+
+    /// Writes `data` at the cursor of the current buffer and returns the element offset it was
+    /// written to. The caller must ensure the data fits.
+    pub fn write(&mut self, gl: &glow::Context, data: &[u8]) -> usize {
+        let offset = self.cursor;
+        let Some(buffer) = self.current() else {
+            return offset;
+        };
+        unsafe {
+            gl.bind_buffer(self.target, Some(buffer));
+            gl.buffer_sub_data_u8_slice(self.target, (offset * self.stride) as i32, data);
+        }
+        self.cursor += data.len() / self.stride;
+        self.peak = self.peak.max(self.cursor);
+        self.bytes_written += data.len();
+        offset
+    }
+
+    /// Reallocates the current buffer so writes do not have to wait for the GPU to finish
+    /// reading its old storage, starting a fresh range at 0.
+    pub fn orphan(&mut self, gl: &glow::Context) {
+        if self.buffers.is_empty() {
+            return;
+        }
+        self.cursor = 0;
+        self.allocate(gl, self.current);
+    }
+
+    /// Rotates to the next buffer in the ring.
+    pub fn swap(&mut self) {
+        if self.buffers.is_empty() {
+            return;
+        }
+        self.cursor = 0;
+        self.current = (self.current + 1) % self.buffers.len();
+    }
+
+    /// Reallocates the current buffer if an earlier growth left it smaller than the others.
+    pub fn ensure_capacity(&mut self, gl: &glow::Context) {
+        if self.capacities.get(self.current).is_some_and(|c| *c < self.len) {
+            self.cursor = 0;
+            self.allocate(gl, self.current);
+        }
+    }
+
+    /// Deletes every buffer in the ring.
+    pub fn destroy(&mut self, gl: &glow::Context) {
+        unsafe {
+            for buffer in self.buffers.drain(..) {
+                gl.delete_buffer(buffer);
+            }
+        }
+        self.capacities.clear();
+        self.current = 0;
+        self.cursor = 0;
+    }
+}
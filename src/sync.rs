@@ -0,0 +1,77 @@
+use glow::HasContext as _;
+
+use crate::Glox;
+
+/// How long to wait for the GPU before orphaning the buffers instead, in nanoseconds. WebGL
+/// does not allow blocking, so there the fence is only polled.
+const WAIT_TIMEOUT: i32 = if cfg!(target_arch = "wasm32") { 0 } else { 1_000_000_000 };
+
+/// Whether the GPU may still be reading the streaming buffers of a ring slot.
+#[derive(Default, Debug)]
+pub(crate) enum SlotSync {
+    /// Not written since the GPU was last known to be done with it.
+    #[default]
+    Free,
+    /// Written in a frame that ends with this fence.
+    Fenced(glow::Fence),
+    /// Written, but the context does not support sync objects.
+    Unfenced,
+}
+
+impl Glox {
+    /// Marks the end of the GPU commands reading the current ring slot.
+    pub(crate) fn fence_slot(&mut self, gl: &glow::Context) {
+        let index = self.vertex_stream.current_index();
+        let Some(slot) = self.slots.get_mut(index) else {
+            return;
+        };
+        if let SlotSync::Fenced(fence) = std::mem::take(slot) {
+            unsafe { gl.delete_sync(fence) };
+        }
+        *slot = match unsafe { gl.fence_sync(glow::SYNC_GPU_COMMANDS_COMPLETE, 0) } {
+            Ok(fence) => SlotSync::Fenced(fence),
+            Err(_) => SlotSync::Unfenced,
+        };
+    }
+
+    /// Makes the current ring slot safe to write, waiting for its fence or orphaning its buffers
+    /// if the fence does not signal in time or is not supported. Must be called with one of our
+    /// vertex arrays bound, as orphaning the index buffer goes through its element array binding.
+    pub(crate) fn sync_slot(&mut self, gl: &glow::Context) {
+        let index = self.vertex_stream.current_index();
+        let Some(slot) = self.slots.get_mut(index) else {
+            return;
+        };
+        let done = match std::mem::take(slot) {
+            SlotSync::Free => true,
+            SlotSync::Unfenced => false,
+            SlotSync::Fenced(fence) => {
+                #[cfg(not(target_arch = "wasm32"))]
+                let start = std::time::Instant::now();
+                let flags = glow::SYNC_FLUSH_COMMANDS_BIT;
+                let status = unsafe { gl.client_wait_sync(fence, flags, WAIT_TIMEOUT) };
+                #[cfg(not(target_arch = "wasm32"))]
+                {
+                    self.frame_stats.fence_wait += start.elapsed();
+                }
+                unsafe { gl.delete_sync(fence) };
+                status == glow::ALREADY_SIGNALED || status == glow::CONDITION_SATISFIED
+            }
+        };
+        if !done {
+            self.vertex_stream.orphan(gl);
+            self.index_stream.orphan(gl);
+            self.instance_stream.orphan(gl);
+            self.frame_stats.buffer_orphans += 1;
+        }
+    }
+
+    /// Deletes every pending fence.
+    pub(crate) fn delete_fences(&mut self, gl: &glow::Context) {
+        for slot in self.slots.drain(..) {
+            if let SlotSync::Fenced(fence) = slot {
+                unsafe { gl.delete_sync(fence) };
+            }
+        }
+    }
+}
@@ -0,0 +1,314 @@
+use glam::UVec2;
+use glow::HasContext as _;
+
+use crate::{Glox, GloxError};
+
+/// Pixel format of a texture created with `Glox::create_texture`.
+#[derive(Default, Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum TextureFormat {
+    /// 4 bytes per pixel, linear color.
+    #[default]
+    Rgba8,
+    /// 1 byte per pixel, sampled as `(r, 0, 0, 1)`.
+    R8,
+    /// 4 bytes per pixel, sRGB color with linear alpha, decoded to linear when sampled.
+    Srgba8,
+}
+
+impl TextureFormat {
+    pub fn bytes_per_pixel(self) -> usize {
+        match self {
+            Self::Rgba8 | Self::Srgba8 => 4,
+            Self::R8 => 1,
+        }
+    }
+
+    fn internal_format(self) -> u32 {
+        match self {
+            Self::Rgba8 => glow::RGBA8,
+            Self::R8 => glow::R8,
+            Self::Srgba8 => glow::SRGB8_ALPHA8,
+        }
+    }
+
+    fn gl_format(self) -> u32 {
+        match self {
+            Self::Rgba8 | Self::Srgba8 => glow::RGBA,
+            Self::R8 => glow::RED,
+        }
+    }
+}
+
+/// How a texture is sampled between and across texels.
+#[derive(Default, Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum TextureFilter {
+    #[default]
+    Nearest,
+    Linear,
+    /// Linear filtering between linearly filtered mip levels, which are generated automatically.
+    Mipmapped,
+}
+
+/// How uvs outside of 0..1 are handled.
+#[derive(Default, Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum TextureWrap {
+    #[default]
+    Repeat,
+    ClampToEdge,
+    MirroredRepeat,
+}
+
+impl TextureWrap {
+    fn to_gl(self) -> u32 {
+        match self {
+            Self::Repeat => glow::REPEAT,
+            Self::ClampToEdge => glow::CLAMP_TO_EDGE,
+            Self::MirroredRepeat => glow::MIRRORED_REPEAT,
+        }
+    }
+}
+
+/// Options for `Glox::create_texture`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct TextureOptions {
+    pub format: TextureFormat,
+    pub filter: TextureFilter,
+    pub wrap_s: TextureWrap,
+    pub wrap_t: TextureWrap,
+    /// Maximum anisotropic filtering, 1.0 disables it. Clamped to what the driver supports and
+    /// ignored if `EXT_texture_filter_anisotropic` is not available.
+    pub anisotropy: f32,
+}
+
+impl Default for TextureOptions {
+    fn default() -> Self {
+        Self {
+            format: TextureFormat::default(),
+            filter: TextureFilter::default(),
+            wrap_s: TextureWrap::default(),
+            wrap_t: TextureWrap::default(),
+            anisotropy: 1.0,
+        }
+    }
+}
+
+/// A texture created by `Glox::create_texture`. Must be freed with `Glox::delete_texture`.
+#[derive(Debug, PartialEq, Eq, Hash)]
+pub struct TextureHandle {
+    texture: glow::Texture,
+    width: u32,
+    height: u32,
+    format: TextureFormat,
+    mipmapped: bool,
+}
+
+impl TextureHandle {
+    /// The underlying GL texture, e.g. for `DrawBuilder::bind_texture`.
+    pub fn raw(&self) -> glow::Texture {
+        self.texture
+    }
+
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    pub fn format(&self) -> TextureFormat {
+        self.format
+    }
+}
+
+/// Tightly packed RGBA8 pixels, rows top to bottom.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Image {
+    pub width: u32,
+    pub height: u32,
+    pub pixels: Vec<u8>,
+}
+
+impl Image {
+    /// Multiplies the color channels by alpha, for use with `BlendMode::PremultipliedAlpha`.
+    pub fn premultiply(&mut self) {
+        for pixel in self.pixels.chunks_exact_mut(4) {
+            let a = pixel[3] as u32;
+            for c in &mut pixel[..3] {
+                *c = ((*c as u32 * a + 127) / 255) as u8;
+            }
+        }
+    }
+
+    /// Makes every pixel with the color `key` fully transparent.
+    pub fn apply_color_key(&mut self, key: [u8; 3]) {
+        for pixel in self.pixels.chunks_exact_mut(4) {
+            if pixel[..3] == key {
+                pixel[3] = 0;
+            }
+        }
+    }
+}
+
+pub(crate) fn check_len(
+    format: TextureFormat,
+    width: u32,
+    height: u32,
+    data: &[u8],
+) -> Result<(), GloxError> {
+    let expected = width as usize * height as usize * format.bytes_per_pixel();
+    if data.len() != expected {
+        return Err(GloxError::InvalidTextureData {
+            expected,
+            actual: data.len(),
+        });
+    }
+    Ok(())
+}
+
+impl Glox {
+    /// Creates a `width` x `height` texture from tightly packed rows of `data` in
+    /// `options.format`.
+    pub fn create_texture(
+        &mut self,
+        gl: &glow::Context,
+        width: u32,
+        height: u32,
+        data: &[u8],
+        options: TextureOptions,
+    ) -> Result<TextureHandle, GloxError> {
+        check_len(options.format, width, height, data)?;
+        unsafe {
+            let texture = gl
+                .create_texture()
+                .map_err(|message| GloxError::ResourceCreation {
+                    resource: "texture",
+                    message,
+                })?;
+            self.state.bind_texture(gl, Some(texture));
+            // rows are tightly packed, the alignment of the host is restored below
+            let alignment = gl.get_parameter_i32(glow::UNPACK_ALIGNMENT);
+            gl.pixel_store_i32(glow::UNPACK_ALIGNMENT, 1);
+            gl.tex_image_2d(
+                glow::TEXTURE_2D,
+                0,
+                options.format.internal_format() as i32,
+                width as i32,
+                height as i32,
+                0,
+                options.format.gl_format(),
+                glow::UNSIGNED_BYTE,
+                glow::PixelUnpackData::Slice(Some(data)),
+            );
+            gl.pixel_store_i32(glow::UNPACK_ALIGNMENT, alignment);
+
+            let (min_filter, mag_filter) = match options.filter {
+                TextureFilter::Nearest => (glow::NEAREST, glow::NEAREST),
+                TextureFilter::Linear => (glow::LINEAR, glow::LINEAR),
+                TextureFilter::Mipmapped => (glow::LINEAR_MIPMAP_LINEAR, glow::LINEAR),
+            };
+            gl.tex_parameter_i32(glow::TEXTURE_2D, glow::TEXTURE_MIN_FILTER, min_filter as i32);
+            gl.tex_parameter_i32(glow::TEXTURE_2D, glow::TEXTURE_MAG_FILTER, mag_filter as i32);
+            gl.tex_parameter_i32(
+                glow::TEXTURE_2D,
+                glow::TEXTURE_WRAP_S,
+                options.wrap_s.to_gl() as i32,
+            );
+            gl.tex_parameter_i32(
+                glow::TEXTURE_2D,
+                glow::TEXTURE_WRAP_T,
+                options.wrap_t.to_gl() as i32,
+            );
+
+            if options.anisotropy > 1.0 && supports_anisotropy(gl) {
+                let max = gl.get_parameter_f32(glow::MAX_TEXTURE_MAX_ANISOTROPY_EXT);
+                gl.tex_parameter_f32(
+                    glow::TEXTURE_2D,
+                    glow::TEXTURE_MAX_ANISOTROPY_EXT,
+                    options.anisotropy.min(max),
+                );
+            }
+
+            let mipmapped = options.filter == TextureFilter::Mipmapped;
+            if mipmapped {
+                gl.generate_mipmap(glow::TEXTURE_2D);
+            }
+
+            Ok(TextureHandle {
+                texture,
+                width,
+                height,
+                format: options.format,
+                mipmapped,
+            })
+        }
+    }
+
+    /// Creates an RGBA texture from `image`. `options.format` must be `Rgba8` or `Srgba8`.
+    pub fn create_texture_from_image(
+        &mut self,
+        gl: &glow::Context,
+        image: &Image,
+        options: TextureOptions,
+    ) -> Result<TextureHandle, GloxError> {
+        self.create_texture(gl, image.width, image.height, &image.pixels, options)
+    }
+
+    /// Replaces the region of `size` texels at `offset` in `texture` with `data`, given in the
+    /// format of the texture. Mip levels are regenerated if the texture is mipmapped.
+    pub fn update_texture(
+        &mut self,
+        gl: &glow::Context,
+        texture: &TextureHandle,
+        offset: UVec2,
+        size: UVec2,
+        data: &[u8],
+    ) -> Result<(), GloxError> {
+        check_len(texture.format, size.x, size.y, data)?;
+        let end_x = offset.x.checked_add(size.x);
+        let end_y = offset.y.checked_add(size.y);
+        let (Some(end_x), Some(end_y)) = (end_x, end_y) else {
+            return Err(GloxError::TextureRegionOutOfBounds);
+        };
+        if end_x > texture.width || end_y > texture.height {
+            return Err(GloxError::TextureRegionOutOfBounds);
+        }
+        self.state.bind_texture(gl, Some(texture.texture));
+        unsafe {
+            // rows are tightly packed, the alignment of the host is restored below
+            let alignment = gl.get_parameter_i32(glow::UNPACK_ALIGNMENT);
+            gl.pixel_store_i32(glow::UNPACK_ALIGNMENT, 1);
+            gl.tex_sub_image_2d(
+                glow::TEXTURE_2D,
+                0,
+                offset.x as i32,
+                offset.y as i32,
+                size.x as i32,
+                size.y as i32,
+                texture.format.gl_format(),
+                glow::UNSIGNED_BYTE,
+                glow::PixelUnpackData::Slice(Some(data)),
+            );
+            gl.pixel_store_i32(glow::UNPACK_ALIGNMENT, alignment);
+            if texture.mipmapped {
+                gl.generate_mipmap(glow::TEXTURE_2D);
+            }
+        }
+        Ok(())
+    }
+
+    /// Deletes a texture created with `create_texture`.
+    pub fn delete_texture(&mut self, gl: &glow::Context, texture: TextureHandle) {
+        self.state.forget_texture(texture.texture);
+        unsafe {
+            gl.delete_texture(texture.texture);
+        }
+    }
+}
+
+fn supports_anisotropy(gl: &glow::Context) -> bool {
+    let extensions = gl.supported_extensions();
+    extensions.contains("GL_EXT_texture_filter_anisotropic")
+        || extensions.contains("GL_ARB_texture_filter_anisotropic")
+        || extensions.contains("EXT_texture_filter_anisotropic")
+}
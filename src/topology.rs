@@ -0,0 +1,26 @@
+/// How the vertices of a draw are assembled into primitives.
+#[derive(Default, Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Topology {
+    #[default]
+    Triangles,
+    /// Every vertex after the first two forms a triangle with the previous two.
+    /// Consecutive pushes are joined into one strip.
+    TriangleStrip,
+    Lines,
+    /// Consecutive pushes are joined into one strip.
+    LineStrip,
+    /// Point size is set with `DrawBuilder::point_size`.
+    Points,
+}
+
+impl Topology {
+    pub fn to_gl(self) -> u32 {
+        match self {
+            Self::Triangles => glow::TRIANGLES,
+            Self::TriangleStrip => glow::TRIANGLE_STRIP,
+            Self::Lines => glow::LINES,
+            Self::LineStrip => glow::LINE_STRIP,
+            Self::Points => glow::POINTS,
+        }
+    }
+}
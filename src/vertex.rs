@@ -1,5 +1,7 @@
 use glam::{Vec2, Vec3, Vec4};
 
+use crate::{AttributeType, Pod, VertexAttribute, VertexLayout};
+
 #[repr(C, packed)]
 #[derive(Default, Clone, Copy, Debug)]
 pub struct Vertex {
@@ -17,3 +19,71 @@ impl Vertex {
         }
     }
 }
+
+/// A rectangle in texture space, e.g. a region of an `Atlas`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct UvRect {
+    pub min: Vec2,
+    pub max: Vec2,
+}
+
+impl Default for UvRect {
+    fn default() -> Self {
+        Self::FULL
+    }
+}
+
+impl UvRect {
+    /// The whole texture.
+    pub const FULL: Self = Self {
+        min: Vec2::ZERO,
+        max: Vec2::ONE,
+    };
+
+    pub fn new(min: Vec2, max: Vec2) -> Self {
+        Self { min, max }
+    }
+
+    pub fn size(&self) -> Vec2 {
+        self.max - self.min
+    }
+
+    /// Maps `uv` in 0..1 into the rectangle.
+    pub fn map(&self, uv: Vec2) -> Vec2 {
+        self.min + uv * self.size()
+    }
+
+    /// Offset (`xy`) and size (`zw`), as used by `Instance::uv_rect`.
+    pub fn to_offset_size(&self) -> Vec4 {
+        self.min.extend(self.size().x).extend(self.size().y)
+    }
+}
+
+// SAFETY: `Vertex` is `repr(C, packed)` and made of floats only
+unsafe impl Pod for Vertex {}
+
+impl VertexLayout for Vertex {
+    const ATTRIBUTES: &'static [VertexAttribute] = &[
+        VertexAttribute {
+            location: 0,
+            components: 3,
+            ty: AttributeType::F32,
+            normalized: false,
+            offset: std::mem::offset_of!(Vertex, position),
+        },
+        VertexAttribute {
+            location: 1,
+            components: 4,
+            ty: AttributeType::F32,
+            normalized: false,
+            offset: std::mem::offset_of!(Vertex, color),
+        },
+        VertexAttribute {
+            location: 2,
+            components: 2,
+            ty: AttributeType::F32,
+            normalized: false,
+            offset: std::mem::offset_of!(Vertex, uv),
+        },
+    ];
+}
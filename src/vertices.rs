@@ -1,5 +1,5 @@
 use ply_rs as ply;
-use crate::Vertex;
+use crate::{GloxError, LitVertex, UvRect, Vertex};
 use glam::{Vec2, Vec3, Vec3Swizzles as _, Vec4};
 pub fn transform_vertices(vertices: &mut [Vertex], translation: Vec3, scaling: Vec3) -> impl Iterator<Item = Vertex> {
     vertices.iter().map(move |vertex| {
@@ -14,21 +14,40 @@ pub fn transform_vertices(vertices: &mut [Vertex], translation: Vec3, scaling: V
 }   
 
 
-pub fn floor_vertices(center: Vec3, color: Vec4) -> [Vertex; 6] {
+/// Adds `normal` to generated vertices, zero for those that are not lit.
+fn lit<V: From<LitVertex>>(normal: Vec3) -> impl Fn(Vertex) -> V {
+    move |vertex| V::from(LitVertex::from_vertex(vertex, normal))
+}
+
+/// Indices of the two triangles making up a quad returned by the `*_indexed` functions.
+pub const QUAD_INDICES: [u32; 6] = [0, 1, 2, 2, 3, 0];
+
+/// Expands an indexed quad into the 6 vertices of its two triangles.
+pub fn quad_to_triangles<T: Copy>(quad: [T; 4]) -> [T; 6] {
+    QUAD_INDICES.map(|i| quad[i as usize])
+}
+
+pub fn floor_vertices<V: From<LitVertex>>(center: Vec3, color: Vec4, uv: UvRect) -> [V; 6] {
+    quad_to_triangles(floor_vertices_indexed::<LitVertex>(center, color, uv).0).map(V::from)
+}
+
+pub fn floor_vertices_indexed<V: From<LitVertex>>(center: Vec3, color: Vec4, uv: UvRect) -> ([V; 4], [u32; 6]) {
     let half_size = 0.5;
     let c = center;
 
-    [
-        Vertex::new(Vec3::new(c.x - half_size, c.y + half_size, c.z), color, [0.0, 0.0].into()),
-        Vertex::new(Vec3::new(c.x - half_size, c.y - half_size, c.z), color, [0.0, 1.0].into()),
-        Vertex::new(Vec3::new(c.x + half_size, c.y - half_size, c.z), color, [1.0, 1.0].into()),
-        Vertex::new(Vec3::new(c.x + half_size, c.y - half_size, c.z), color, [1.0, 1.0].into()),
-        Vertex::new(Vec3::new(c.x + half_size, c.y + half_size, c.z), color, [1.0, 0.0].into()),
-        Vertex::new(Vec3::new(c.x - half_size, c.y + half_size, c.z), color, [0.0, 0.0].into()),
-    ]
+    (
+        [
+            Vertex::new(Vec3::new(c.x - half_size, c.y + half_size, c.z), color, uv.map(Vec2::new(0.0, 0.0))),
+            Vertex::new(Vec3::new(c.x - half_size, c.y - half_size, c.z), color, uv.map(Vec2::new(0.0, 1.0))),
+            Vertex::new(Vec3::new(c.x + half_size, c.y - half_size, c.z), color, uv.map(Vec2::new(1.0, 1.0))),
+            Vertex::new(Vec3::new(c.x + half_size, c.y + half_size, c.z), color, uv.map(Vec2::new(1.0, 0.0))),
+        ]
+        .map(lit(Vec3::Z)),
+        QUAD_INDICES,
+    )
 }
 
-pub fn plane_vertices(center: Vec3, color: Vec4, size: f32) -> [Vertex; 6] {
+pub fn plane_vertices<V: From<LitVertex>>(center: Vec3, color: Vec4, size: f32) -> [V; 6] {
     let half_size = size / 2.0;
     let c = center;
 
@@ -40,65 +59,108 @@ pub fn plane_vertices(center: Vec3, color: Vec4, size: f32) -> [Vertex; 6] {
         Vertex::new(Vec3::new(c.x + half_size, c.y + half_size, c.z), color, [1.0, 0.0].into()),
         Vertex::new(Vec3::new(c.x - half_size, c.y + half_size, c.z), color, [0.0, 0.0].into()),
     ]
+    .map(lit(Vec3::Z))
 }
 
 
 
-pub fn ply_vertices(source:&str) -> Result<Vec<Vertex>, ()> {
-    let mut vertices = Vec::new();
+/// Reads a vertex element with float `x`, `y`, `z` and uchar `red`, `green`, `blue` properties,
+/// and optionally uchar `alpha`, defaulting to opaque, and float `nx`, `ny`, `nz` normals.
+fn ply_vertex(v: &ply::ply::DefaultElement) -> Option<LitVertex> {
+    let Some(ply_rs::ply::Property::Float(x)) = v.get("x") else { return None };
+    let Some(ply_rs::ply::Property::Float(y)) = v.get("y") else { return None };
+    let Some(ply_rs::ply::Property::Float(z)) = v.get("z") else { return None };
+    let Some(ply_rs::ply::Property::UChar(r)) = v.get("red") else { return None };
+    let Some(ply_rs::ply::Property::UChar(g)) = v.get("green") else { return None };
+    let Some(ply_rs::ply::Property::UChar(b)) = v.get("blue") else { return None };
+    // scanners commonly leave out alpha
+    let a = match v.get("alpha") {
+        Some(ply_rs::ply::Property::UChar(a)) => *a,
+        None => 255,
+        Some(_) => return None,
+    };
+    let r = *r as f32 / 255.0;
+    let g = *g as f32 / 255.0;
+    let b = *b as f32 / 255.0;
+    let a = a as f32 / 255.0;
+    let normal = match (v.get("nx"), v.get("ny"), v.get("nz")) {
+        (
+            Some(ply_rs::ply::Property::Float(nx)),
+            Some(ply_rs::ply::Property::Float(ny)),
+            Some(ply_rs::ply::Property::Float(nz)),
+        ) => [*nx, *ny, *nz],
+        _ => [0.0; 3],
+    };
+    Some(LitVertex { position: [*x, *y, *z], color: [r, g, b, a], uv: [0.0, 0.0], normal })
+}
+
+fn parse_ply(source: &str) -> Result<ply::ply::Ply<ply::ply::DefaultElement>, GloxError> {
     let p = ply::parser::Parser::<ply::ply::DefaultElement>::new();
     let mut reader = std::io::Cursor::new(source);
-    match p.read_ply(&mut reader) { 
+    p.read_ply(&mut reader).map_err(|_| GloxError::InvalidPly)
+}
+
+/// Every vertex of a PLY source in file order, ignoring faces, e.g. to draw a point cloud scan
+/// with `Topology::Points`.
+pub fn ply_points<V: From<LitVertex>>(source: &str) -> Result<Vec<V>, GloxError> {
+    let ply = parse_ply(source)?;
+    let Some(vertices) = ply.payload.get("vertex") else { return Err(GloxError::InvalidPly); };
+    vertices
+        .iter()
+        .map(|v| ply_vertex(v).map(V::from).ok_or(GloxError::InvalidPly))
+        .collect()
+}
+
+/// The triangles of a PLY source, three vertices per triangle. Faces that are not triangles
+/// are skipped. Use `ply_points` for sources without faces.
+pub fn ply_vertices<V: From<LitVertex>>(source:&str) -> Result<Vec<V>, GloxError> {
+    let mut vertices = Vec::new();
+    match parse_ply(source) {
         Err(_) => {
-            return Err(());
+            return Err(GloxError::InvalidPly);
         },
         Ok(p) => {
-            let Some(faces) = p.payload.get("face") else { return Err(()); };
-            let Some(vertex) = p.payload.get("vertex") else { return Err(()); };
-            let vertex = |i:usize| -> Option<Vertex> {
-                let Some(v) = &vertex.get(i) else { return None };
-                let Some(ply_rs::ply::Property::Float(x)) = v.get("x") else { return None };
-                let Some(ply_rs::ply::Property::Float(y)) = v.get("y") else { return None };
-                let Some(ply_rs::ply::Property::Float(z)) = v.get("z") else { return None };
-                let Some(ply_rs::ply::Property::UChar(r)) = v.get("red") else { return None };
-                let Some(ply_rs::ply::Property::UChar(g)) = v.get("green") else { return None };
-                let Some(ply_rs::ply::Property::UChar(b)) = v.get("blue") else { return None };
-                let Some(ply_rs::ply::Property::UChar(a)) = v.get("alpha") else { return None };
-                let r = *r as f32 / 255.0;
-                let g = *g as f32 / 255.0;
-                let b = *b as f32 / 255.0;
-                let a = *a as f32 / 255.0;
-                Some(Vertex { position: [*x, *y, *z], color: [r, g, b, a], uv: [0.0, 0.0] })
-            };
+            let Some(faces) = p.payload.get("face") else { return Err(GloxError::InvalidPly); };
+            let Some(vertex) = p.payload.get("vertex") else { return Err(GloxError::InvalidPly); };
+            let vertex = |i:usize| vertex.get(i).and_then(ply_vertex);
             for face in faces.iter() {
-                let Some(vertex_indices) = face.get("vertex_indices") else { return Err(()); };
-                match vertex_indices {
-                    ply_rs::ply::Property::ListUInt(index) => {
-                        if index.len() != 3 {
-                            continue;
-                        }
-                        let v0 = index[0] as usize;
-                        let v1 = index[1] as usize;
-                        let v2 = index[2] as usize;
-                        let Some(v0) = vertex(v0) else { return Err(()); };
-                        let Some(v1) = vertex(v1) else { return Err(()); };
-                        let Some(v2) = vertex(v2) else { return Err(()); };
-                        vertices.push(v0);
-                        vertices.push(v1);
-                        vertices.push(v2);
-                    },
-                    _=> {}
+                let Some(vertex_indices) = face.get("vertex_indices") else { return Err(GloxError::InvalidPly); };
+                if let ply_rs::ply::Property::ListUInt(index) = vertex_indices {
+                    if index.len() != 3 {
+                        continue;
+                    }
+                    let v0 = index[0] as usize;
+                    let v1 = index[1] as usize;
+                    let v2 = index[2] as usize;
+                    let Some(v0) = vertex(v0) else { return Err(GloxError::InvalidPly); };
+                    let Some(v1) = vertex(v1) else { return Err(GloxError::InvalidPly); };
+                    let Some(v2) = vertex(v2) else { return Err(GloxError::InvalidPly); };
+                    let p0 = Vec3::from(v0.position);
+                    let face_normal = (Vec3::from(v1.position) - p0).cross(Vec3::from(v2.position) - p0).normalize_or_zero();
+                    for v in [v0, v1, v2] {
+                        // files without normals are lit per face
+                        let normal = v.normal;
+                        let normal = if normal == [0.0; 3] { face_normal } else { Vec3::from(normal) };
+                        vertices.push(v.with_normal(normal));
+                    }
                 }
             }
         }
     };
 
-    Ok(vertices)
+    Ok(vertices.into_iter().map(V::from).collect())
+}
+
+pub fn wall_vertices<V: From<LitVertex>>(bottom_center: Vec3, height:f32, color: Vec4, normal: Vec3, uv: UvRect) -> [V; 6] {
+    quad_to_triangles(wall_vertices_indexed::<LitVertex>(bottom_center, height, color, normal, uv).0).map(V::from)
 }
 
-pub fn wall_vertices(bottom_center: Vec3, height:f32, color: Vec4, normal: Vec3) -> [Vertex; 6] {
+/// A wall whose front side, wound counter-clockwise, faces along `normal`. The texture region
+/// `uv` is mapped onto the whole wall.
+pub fn wall_vertices_indexed<V: From<LitVertex>>(bottom_center: Vec3, height:f32, color: Vec4, normal: Vec3, uv: UvRect) -> ([V; 4], [u32; 6]) {
     let up = Vec3::new(0.0, 0.0, 1.0);
-    let right = normal.cross(up).normalize();
+    let normal = normal.normalize();
+    let right = up.cross(normal).normalize();
     let half_width = 0.5;
 
     let top_center = bottom_center + up * height;
@@ -108,23 +170,26 @@ pub fn wall_vertices(bottom_center: Vec3, height:f32, color: Vec4, normal: Vec3)
     let tl = top_center - right * half_width; // top left
     let tr = top_center + right * half_width; // top right
 
-    [
-        Vertex::new(bl, color, [0.0, 1.0].into()),
-        Vertex::new(br, color, [1.0, 1.0].into()),
-        Vertex::new(tr, color, [1.0, 0.0].into()),
-        Vertex::new(tr, color, [1.0, 0.0].into()),
-        Vertex::new(tl, color, [0.0, 0.0].into()),
-        Vertex::new(bl, color, [0.0, 1.0].into()),
-    ]
+    (
+        [
+            Vertex::new(bl, color, uv.map(Vec2::new(0.0, 1.0))),
+            Vertex::new(br, color, uv.map(Vec2::new(1.0, 1.0))),
+            Vertex::new(tr, color, uv.map(Vec2::new(1.0, 0.0))),
+            Vertex::new(tl, color, uv.map(Vec2::new(0.0, 0.0))),
+        ]
+        .map(lit(normal)),
+        QUAD_INDICES,
+    )
 }
 
-pub fn line_vertices(start: Vec3, end: Vec3, width: f32, color: Vec4, camera_dir: Vec3) -> [Vertex; 6] {
+pub fn line_vertices<V: From<LitVertex>>(start: Vec3, end: Vec3, width: f32, color: Vec4, camera_dir: Vec3) -> [V; 6] {
     // Calculate line direction and perpendicular vector facing camera
     let line_dir = (end - start).normalize();
     let camera_dir_normalized = camera_dir.normalize();
     
-    // Calculate right vector perpendicular to both line direction and camera direction
-    let right = line_dir.cross(camera_dir_normalized).normalize();
+    // Calculate right vector perpendicular to both line direction and camera direction,
+    // winding the quad counter-clockwise as seen from the camera
+    let right = camera_dir_normalized.cross(line_dir).normalize();
     let half_width = width * 0.5;
     
     // Calculate quad corners
@@ -141,14 +206,21 @@ pub fn line_vertices(start: Vec3, end: Vec3, width: f32, color: Vec4, camera_dir
         Vertex::new(end_left, color, [0.0, 1.0].into()),
         Vertex::new(start_left, color, [0.0, 0.0].into()),
     ]
+    .map(lit(Vec3::ZERO))
 }   
 
 
-pub fn billboard_vertices(bottom_center: Vec3, color: Vec4, camera_dir: Vec3, scaling_factor: Vec2) -> [Vertex; 6] {
+pub fn billboard_vertices<V: From<LitVertex>>(bottom_center: Vec3, color: Vec4, camera_dir: Vec3, scaling_factor: Vec2, uv: UvRect) -> [V; 6] {
+    quad_to_triangles(billboard_vertices_indexed::<LitVertex>(bottom_center, color, camera_dir, scaling_factor, uv).0).map(V::from)
+}
+
+/// A billboard rotated around the z axis so its front side faces the camera. The texture region
+/// `uv` is mapped onto the whole billboard.
+pub fn billboard_vertices_indexed<V: From<LitVertex>>(bottom_center: Vec3, color: Vec4, camera_dir: Vec3, scaling_factor: Vec2, uv: UvRect) -> ([V; 4], [u32; 6]) {
     let up = Vec3::new(0.0, 0.0, 1.0);
     let normal = camera_dir.xy() * -1.0;
     let normal = normal.extend(0.0);
-    let right = normal.cross(up).normalize();
+    let right = up.cross(normal).normalize();
     let half_width = 0.5 * scaling_factor.x;
     let height = 1.0 * scaling_factor.y;
 
@@ -159,12 +231,49 @@ pub fn billboard_vertices(bottom_center: Vec3, color: Vec4, camera_dir: Vec3, sc
     let tl = top_center - right * half_width; // top left
     let tr = top_center + right * half_width; // top right
 
-    [
-        Vertex::new(bl, color, [0.0, 1.0].into()),
-        Vertex::new(br, color, [1.0, 1.0].into()),
-        Vertex::new(tr, color, [1.0, 0.0].into()),
-        Vertex::new(tr, color, [1.0, 0.0].into()),
-        Vertex::new(tl, color, [0.0, 0.0].into()),
-        Vertex::new(bl, color, [0.0, 1.0].into()),
-    ]
+    (
+        [
+            Vertex::new(bl, color, uv.map(Vec2::new(0.0, 1.0))),
+            Vertex::new(br, color, uv.map(Vec2::new(1.0, 1.0))),
+            Vertex::new(tr, color, uv.map(Vec2::new(1.0, 0.0))),
+            Vertex::new(tl, color, uv.map(Vec2::new(0.0, 0.0))),
+        ]
+        .map(lit(Vec3::ZERO)),
+        QUAD_INDICES,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const POINTS: &str = "ply
+format ascii 1.0
+element vertex 2
+property float x
+property float y
+property float z
+property uchar red
+property uchar green
+property uchar blue
+end_header
+1 2 3 255 0 0
+-1 0 0.5 0 255 51
+";
+
+    #[test]
+    fn ply_points_without_faces_or_alpha() {
+        let points: Vec<LitVertex> = ply_points(POINTS).unwrap();
+        assert_eq!(points.len(), 2);
+        assert_eq!(points[0].position, [1.0, 2.0, 3.0]);
+        assert_eq!(points[0].color, [1.0, 0.0, 0.0, 1.0]);
+        assert_eq!(points[1].position, [-1.0, 0.0, 0.5]);
+        assert_eq!(points[1].color, [0.0, 1.0, 0.2, 1.0]);
+        assert_eq!(points[1].normal, [0.0; 3]);
+    }
+
+    #[test]
+    fn ply_vertices_requires_faces() {
+        assert_eq!(ply_vertices::<LitVertex>(POINTS), Err(GloxError::InvalidPly));
+    }
 }